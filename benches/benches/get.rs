@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use ksq::Tree;
+use ksq::{Tree, TreeBuilder};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
@@ -83,6 +83,102 @@ pub fn criterion_benchmark(c: &mut Criterion) {
             tree.set(1024);
         });
     });
+
+    let indices: Vec<usize> = {
+        let mut rng = SmallRng::seed_from_u64(0xDEADBEEF);
+        (0..512).map(|_| rng.gen::<usize>() % tree.bits()).collect()
+    };
+
+    group.bench_function("set::rand", |b| {
+        let tree = tree.clone();
+        b.iter(|| {
+            let mut tree = tree.clone();
+            for idx in indices.iter() {
+                tree.set(*idx);
+            }
+        });
+    });
+
+    group.bench_function("set_many::rand", |b| {
+        let tree = tree.clone();
+        b.iter(|| {
+            let mut tree = tree.clone();
+            tree.set_many(&indices);
+        });
+    });
+
+    let queries: Vec<usize> = {
+        let mut rng = SmallRng::seed_from_u64(0xDEADBEEF);
+        let mut queries: Vec<usize> = (0..512).map(|_| rng.gen::<usize>() % tree.bits()).collect();
+        queries.sort_unstable();
+        queries
+    };
+
+    let mut queried = tree.clone();
+    queried.set_many(&indices);
+
+    group.bench_function("get::sorted", |b| {
+        b.iter(|| {
+            for idx in queries.iter() {
+                queried.get(*idx);
+            }
+        });
+    });
+
+    group.bench_function("get_many::sorted", |b| {
+        b.iter(|| queried.get_many(&queries));
+    });
+
+    group.bench_function("get_sorted::sorted", |b| {
+        b.iter(|| queried.get_sorted(&queries));
+    });
+
+    let snapshot = {
+        let mut tree = tree.clone();
+        tree.set_many(&indices);
+        tree
+    };
+
+    group.bench_function("clone::snapshot", |b| {
+        let mut scratch = snapshot.clone();
+        b.iter(|| {
+            scratch.set(0);
+            scratch = snapshot.clone();
+        });
+    });
+
+    group.bench_function("clone_from::snapshot", |b| {
+        let mut scratch = snapshot.clone();
+        b.iter(|| {
+            scratch.set(0);
+            scratch.clone_from(&snapshot);
+        });
+    });
+
+    let sparse: Vec<usize> = {
+        let mut rng = SmallRng::seed_from_u64(0xDEADBEEF);
+        (0..4096).map(|_| rng.gen::<usize>() % (1 << 32)).collect()
+    };
+
+    group.bench_function("set::sparse", |b| {
+        b.iter(|| {
+            let mut tree = Tree::new();
+            for idx in sparse.iter() {
+                tree.grow_to(*idx + 1);
+                tree.set(*idx);
+            }
+        });
+    });
+
+    group.bench_function("builder::sparse", |b| {
+        b.iter(|| {
+            let mut builder = TreeBuilder::new();
+            for idx in sparse.iter() {
+                builder.push(*idx);
+            }
+            builder.build()
+        });
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
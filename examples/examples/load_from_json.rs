@@ -12,11 +12,11 @@ struct Dependency {
 }
 
 fn main() {
-    let mut tree = ksq::Tree::from(&[1, 1, 1, 1, 1, 1, 1, 1, 0]).unwrap();
     const TREE_PER_ROW: usize = 100_000;
     const TREE_TOTAL: usize = TREE_PER_ROW * TREE_PER_ROW;
 
-    assert!(tree.bits() >= TREE_TOTAL);
+    let mut tree = ksq::Tree::new();
+    tree.grow_to(TREE_TOTAL);
 
     for line in std::io::stdin().lines() {
         let line = line.unwrap();
@@ -33,7 +33,5 @@ fn main() {
     }
 
     let mut out = std::fs::File::create("graph.k2").unwrap();
-    for chunk in tree.to_vec() {
-        out.write(&chunk.to_be_bytes()).unwrap();
-    }
+    out.write_all(&tree.to_bytes()).unwrap();
 }
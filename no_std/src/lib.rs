@@ -0,0 +1,42 @@
+#![no_std]
+
+//! This crate exists to check that `ksq` builds and works without `std`
+//! (only `alloc`), when the default `std` feature is disabled. It's not
+//! meant to be used for anything.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use ksq::{Error, Matrix, Tree};
+
+/// Build a [Tree] with only the `ones` bits set, and encode it as bytes.
+pub fn encode(ones: &[usize]) -> Vec<u8> {
+    let tree: Tree = ones.iter().copied().collect();
+    tree.to_bytes()
+}
+
+/// Decode the output of [encode], and return the set bits.
+pub fn decode(bytes: &[u8]) -> Result<Vec<usize>, Error> {
+    Ok(Tree::from_bytes(bytes)?.iter_ones().collect())
+}
+
+/// Return the set (x, y) coordinates of a [Matrix] with the `points` set.
+pub fn matrix_ones(points: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    Matrix::from_ones(points.iter().copied())
+        .iter_ones()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn no_std_round_trip() {
+        let bytes = encode(&[17, 3, 4000]);
+        assert_eq!(Ok(vec![3, 17, 4000]), decode(&bytes));
+        assert_eq!(Err(Error::Empty), decode(&[]));
+        assert_eq!(vec![(1, 0), (3, 2)], matrix_ones(&[(3, 2), (1, 0)]));
+    }
+}
@@ -18,95 +18,227 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE. }}}
 
-use super::CountOnes;
+use super::{
+    CountOnes,
+    std::{
+        fmt::{Binary, Debug},
+        hash::Hash,
+        iter,
+        ops::{BitAnd, BitOr, BitXor, Not, Shl, Sub},
+    },
+};
 
+/// The default integer backing a Cell, and the one used by the encodings
+/// of a [crate::Tree].
 pub(crate) type CellRepr = u16;
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An unsigned integer which may be used as the bit field backing each
+/// Cell of a [crate::Tree]. Wider integers mean a shorter [crate::Tree]
+/// with larger Cells, narrower integers mean a taller [crate::Tree] which
+/// wastes less space on sparse data.
+///
+/// This is implemented for `u8`, `u16`, `u32` and `u64`, and can not be
+/// implemented outside of this crate.
+pub trait CellInt:
+    sealed::Sealed
+    + Copy
+    + Default
+    + Debug
+    + Binary
+    + Eq
+    + Hash
+    + Send
+    + Sync
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Sub<Output = Self>
+{
+    /// Number of bits in the integer.
+    const BITS: u32;
+
+    /// The integer with no bits set.
+    const ZERO: Self;
+
+    /// The integer with only the lowest bit set.
+    const ONE: Self;
+
+    /// Return the number of set bits.
+    fn count_ones(self) -> u32;
+
+    /// Return the number of unset bits above the highest set bit.
+    fn leading_zeros(self) -> u32;
+
+    /// Return the number of unset bits below the lowest set bit.
+    fn trailing_zeros(self) -> u32;
+}
+
+macro_rules! cell_int {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl CellInt for $t {
+                const BITS: u32 = <$t>::BITS;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+
+                fn leading_zeros(self) -> u32 {
+                    <$t>::leading_zeros(self)
+                }
+
+                fn trailing_zeros(self) -> u32 {
+                    <$t>::trailing_zeros(self)
+                }
+            }
+
+            impl From<Cell<$t>> for $t {
+                fn from(n: Cell<$t>) -> Self {
+                    n.0
+                }
+            }
+        )*
+    };
+}
+
+cell_int!(u8, u16, u32, u64);
+
 /// Cell is the lowest level bit field. This is (as an implementation detail)
-/// an integer being used as a bit array. It's currently a u16, but any
-/// assumptions on bit size must use `Cell::bits` instead, as it may
-/// change as performance is tweaked (or maybe at runtime!)
+/// an integer being used as a bit array. It defaults to a u16, but any
+/// assumptions on bit size must use `Cell::bits` instead, since it may be
+/// any [CellInt].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[repr(transparent)]
-pub struct Cell(CellRepr);
+pub struct Cell<T: CellInt = CellRepr>(T);
 
 macro_rules! bounds_check {
-    ($n:expr) => {
-        assert!($n < $crate::Cell::bits(), "out of bounds");
+    ($t:ty, $n:expr) => {
+        assert!($n < $crate::Cell::<$t>::bits(), "out of bounds");
     };
 }
 
-impl CountOnes for Cell {
+impl<T: CellInt> CountOnes for Cell<T> {
     fn count_ones(&self) -> usize {
         self.0.count_ones() as usize
     }
 
     fn count_ones_until(&self, idx: usize) -> usize {
-        bounds_check!(idx);
-        (self.0 & ((1 << idx) - 1)).count_ones() as usize
+        bounds_check!(T, idx);
+        (self.0 & ((T::ONE << idx) - T::ONE)).count_ones() as usize
     }
 }
 
-impl From<CellRepr> for Cell {
-    fn from(n: CellRepr) -> Self {
+impl<T: CellInt> From<T> for Cell<T> {
+    fn from(n: T) -> Self {
         Self(n)
     }
 }
 
-impl From<Cell> for CellRepr {
-    fn from(n: Cell) -> Self {
-        n.0
-    }
-}
-
-impl Cell {
+impl<T: CellInt> Cell<T> {
     /// Return a new [Cell].
     pub fn new() -> Self {
-        Self(0)
+        Self(T::ZERO)
     }
 
     /// Return the number of bits stored in a [Cell].
     pub const fn bits() -> usize {
-        16
+        T::BITS as usize
+    }
+
+    /// Return the number of bits needed to address a bit within a [Cell]
+    /// (that is, `log2` of [Cell::bits]). This is how many bits of offset
+    /// each layer of a [crate::Tree] consumes.
+    pub const fn bits_log2() -> usize {
+        T::BITS.trailing_zeros() as usize
     }
 
     /// Return the status of a bit index inside the cell. If the bit is out
     /// of range, this will induce a panic.
     pub fn get(&self, n: usize) -> bool {
-        bounds_check!(n);
+        bounds_check!(T, n);
 
-        if self.0 == 0 {
+        if self.0 == T::ZERO {
             return false;
         }
 
-        (self.0 & (1 << n)) != 0
+        (self.0 & (T::ONE << n)) != T::ZERO
     }
 
     /// Set a bit index, returning the new Cell. If the bit is out of range,
     /// this will induce a panic.
     pub fn set(&self, n: usize, v: bool) -> Self {
-        bounds_check!(n);
+        bounds_check!(T, n);
 
         if v {
-            Self(self.0 | (1 << n))
+            Self(self.0 | (T::ONE << n))
         } else {
-            Self(self.0 & (!(1 << n)))
+            Self(self.0 & (!(T::ONE << n)))
         }
     }
 
-    /// Return the inner type. This may change over time.
-    pub fn inner(&self) -> CellRepr {
+    /// Set every bit which is set in `mask`, returning the new Cell.
+    pub fn with_mask(&self, mask: T) -> Self {
+        Self(self.0 | mask)
+    }
+
+    /// Clear every bit which is set in `mask`, returning the new Cell.
+    pub fn clear_mask(&self, mask: T) -> Self {
+        Self(self.0 & !mask)
+    }
+
+    /// Return the inner type.
+    pub fn inner(&self) -> T {
         self.0
     }
+
+    /// Return the number of unset bits below the lowest set bit of the cell.
+    /// If no bits are set, this returns [Cell::bits].
+    pub fn trailing_zeros(&self) -> u32 {
+        self.0.trailing_zeros().min(Self::bits() as u32)
+    }
+
+    /// Return the number of unset bits above the highest set bit of the cell,
+    /// counting down from [Cell::bits] (rather than the width of the backing
+    /// integer). If no bits are set, this returns [Cell::bits].
+    pub fn leading_zeros(&self) -> u32 {
+        let padding = T::BITS - Self::bits() as u32;
+        self.0.leading_zeros() - padding
+    }
+
+    /// Iterate over the index of each set bit inside the cell, from the
+    /// lowest bit up.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> {
+        let mut v = self.0;
+        iter::from_fn(move || {
+            if v == T::ZERO {
+                return None;
+            }
+            let idx = v.trailing_zeros() as usize;
+            // clear the lowest set bit.
+            v = v & (v - T::ONE);
+            Some(idx)
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::std::{vec, vec::Vec};
 
     #[test]
     fn cell_get_set() {
-        let mut c = Cell::new().set(1, true).set(3, true);
+        let mut c = Cell::<u16>::new().set(1, true).set(3, true);
         assert_eq!(10, c.inner());
 
         assert_eq!(2, c.count_ones());
@@ -126,7 +258,7 @@ mod tests {
     #[test]
     fn cell_get_set_all() {
         for i in 0..16 {
-            let mut c = Cell::new();
+            let mut c = Cell::<u16>::new();
             assert!(!c.get(i));
             c = c.set(i, true);
             assert!(c.get(i));
@@ -137,13 +269,86 @@ mod tests {
 
     #[test]
     fn cell_get_set_all_dirty() {
-        let mut c = Cell::new();
+        let mut c = Cell::<u16>::new();
         for i in 0..16 {
             assert!(!c.get(i));
             c = c.set(i, true);
             assert!(c.get(i));
         }
     }
+
+    #[test]
+    fn cell_with_clear_mask() {
+        let c = Cell::<u16>::new();
+        assert_eq!(c.set(1, true).set(3, true), c.with_mask(0b1010));
+        assert_eq!(c, c.with_mask(0));
+
+        let c = Cell::<u16>::from(0b1111_0000_0000_1111);
+        assert_eq!(0b1111_0000_1010_1111, c.with_mask(0b1010_1010).inner());
+        assert_eq!(0b1111_0000_0000_0101, c.clear_mask(0b1010).inner());
+        assert_eq!(c.set(0, false).set(15, false), c.clear_mask(0x8001));
+        assert_eq!(Cell::new(), c.clear_mask(!0));
+
+        let c = Cell::<u64>::new().with_mask(1 << 63);
+        assert!(c.get(63));
+        assert_eq!(Cell::new(), c.clear_mask(1 << 63));
+    }
+
+    #[test]
+    fn cell_iter_ones() {
+        assert_eq!(
+            vec![1, 3],
+            Cell::<u16>::from(10).iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(0, Cell::<u16>::new().iter_ones().count());
+        assert_eq!(
+            (0..16).collect::<Vec<_>>(),
+            Cell::<u16>::from(0xFFFF).iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![0, 63],
+            Cell::<u64>::from(1 | (1 << 63))
+                .iter_ones()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn cell_leading_trailing_zeros() {
+        let c = Cell::<u16>::new();
+        assert_eq!((16, 16), (c.leading_zeros(), c.trailing_zeros()));
+
+        for i in 0..16 {
+            let c = Cell::<u16>::new().set(i, true);
+            assert_eq!(15 - i as u32, c.leading_zeros());
+            assert_eq!(i as u32, c.trailing_zeros());
+        }
+
+        let c = Cell::<u16>::from(0b0010_0000_0101_0000);
+        assert_eq!((2, 4), (c.leading_zeros(), c.trailing_zeros()));
+
+        let c = Cell::<u64>::from(1 << 40);
+        assert_eq!((23, 40), (c.leading_zeros(), c.trailing_zeros()));
+        let c = Cell::<u8>::new();
+        assert_eq!((8, 8), (c.leading_zeros(), c.trailing_zeros()));
+    }
+
+    #[test]
+    fn cell_int_widths() {
+        assert_eq!((8, 3), (Cell::<u8>::bits(), Cell::<u8>::bits_log2()));
+        assert_eq!((16, 4), (Cell::<u16>::bits(), Cell::<u16>::bits_log2()));
+        assert_eq!((32, 5), (Cell::<u32>::bits(), Cell::<u32>::bits_log2()));
+        assert_eq!((64, 6), (Cell::<u64>::bits(), Cell::<u64>::bits_log2()));
+
+        let c = Cell::<u64>::new().set(63, true).set(40, true);
+        assert!(c.get(63));
+        assert_eq!(1, c.count_ones_until(63));
+        assert_eq!((1 << 63) | (1 << 40), c.inner());
+
+        let c = Cell::<u8>::new().set(7, true);
+        assert_eq!(0x80, c.inner());
+        assert_eq!(0, c.count_ones_until(7));
+    }
 }
 
 // vim: foldmethod=marker
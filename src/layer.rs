@@ -18,7 +18,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE. }}}
 
-use super::{Cell, CellRepr, CountOnes, std::vec::Vec};
+use super::{Cell, CellInt, CellRepr, CountOnes, std::vec::Vec};
 
 /// A [Layer] is a collection of [Cell]s.
 ///
@@ -26,10 +26,22 @@ use super::{Cell, CellRepr, CountOnes, std::vec::Vec};
 /// abstraction to use, usually you want a [crate::Tree]. Very few knobs are
 /// exposed on a Layer (intentionally), since they have to be used within
 /// the context of all the other layers.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Layer(pub(crate) Vec<Cell>);
+#[derive(Debug, PartialEq, Eq)]
+pub struct Layer<T: CellInt = CellRepr>(pub(crate) Vec<Cell<T>>);
 
-impl CountOnes for Layer {
+impl<T: CellInt> Clone for Layer<T> {
+    fn clone(&self) -> Self {
+        Layer(self.0.clone())
+    }
+
+    /// Copy the Cells of `source` into this [Layer], re-using the existing
+    /// allocation where possible.
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
+
+impl<T: CellInt> CountOnes for Layer<T> {
     fn count_ones(&self) -> usize {
         self.0.count_ones()
     }
@@ -39,7 +51,7 @@ impl CountOnes for Layer {
     }
 }
 
-impl CountOnes for [Cell] {
+impl<T: CellInt> CountOnes for [Cell<T>] {
     fn count_ones(&self) -> usize {
         let mut r = 0;
         for cell in self.iter() {
@@ -62,15 +74,15 @@ impl CountOnes for [Cell] {
 /// into the layer.
 pub(crate) type LayerIndex = (usize, usize, usize);
 
-impl Layer {
+impl<T: CellInt> Layer<T> {
     /// Create a [Layer] from some [Cell]s.
-    pub fn from(iter: impl IntoIterator<Item = Cell>) -> Self {
+    pub fn from(iter: impl IntoIterator<Item = Cell<T>>) -> Self {
         Layer(iter.into_iter().collect())
     }
 
     /// Return the total number of bits represented by a cell on this Layer.
     pub(crate) fn layer_bits(height: usize) -> usize {
-        Cell::bits() << (4 * height)
+        Cell::<T>::bits() << (Cell::<T>::bits_log2() * height)
     }
 
     /// Return the bitwise offset within the cell of the provided bit offset
@@ -78,7 +90,7 @@ impl Layer {
     /// which cell and which bit maps to global bit 10 for the higher layers.
     pub(crate) fn cell_bit(&self, li: LayerIndex) -> usize {
         let (height, _, bit) = li;
-        (bit / (Self::layer_bits(height) >> 4)) % Cell::bits()
+        (bit / (Self::layer_bits(height) >> Cell::<T>::bits_log2())) % Cell::<T>::bits()
     }
 
     /// Get the value at some offset, as well as offset information used
@@ -100,7 +112,7 @@ impl Layer {
 
     /// Insert a [Cell] into this layer at the provided index. This is done
     /// if you are adding a newly set bit, or growing the tree.
-    pub(crate) fn insert_cell(&mut self, n: usize, cell: Cell) {
+    pub(crate) fn insert_cell(&mut self, n: usize, cell: Cell<T>) {
         self.0.insert(n, cell);
     }
 
@@ -124,10 +136,10 @@ impl Layer {
         self.0[offset] = self.0[offset].set(o, false);
     }
 
-    /// Turn this layer into a stream of self-describing `u16` values in a
-    /// [Vec] which can be used to re-construct this [Layer].
-    pub(crate) fn to_vec(&self) -> Vec<CellRepr> {
-        self.0.iter().map(|v| (*v).into()).collect()
+    /// Turn this layer into a stream of self-describing [CellInt] values in
+    /// a [Vec] which can be used to re-construct this [Layer].
+    pub(crate) fn to_vec(&self) -> Vec<T> {
+        self.0.iter().map(|v| v.inner()).collect()
     }
 }
 
@@ -137,7 +149,7 @@ mod tests {
 
     #[test]
     fn layer_basic() {
-        let layer = Layer::from([1.into()]);
+        let layer = Layer::<u16>::from([1.into()]);
         let (_, v) = layer.get((0, 0, 0));
         assert!(v);
     }
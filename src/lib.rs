@@ -33,9 +33,18 @@
 //! the tree as a flat 1-d array, rather than explicitly encoding
 //! dimensionality.
 //!
-//! Unlike some other k2 trees, I've opted to use a `u16`, not a `u8`. This
-//! means that the tree will grow by `N<<4` each layer -- and each cell can
-//! represent a maximum of 16 other cells, not 8.
+//! Unlike some other k2 trees, I've opted to use a `u16`, not a `u8`, by
+//! default. This means that the tree will grow by `N<<4` each layer -- and
+//! each cell can represent a maximum of 16 other cells, not 8. Any other
+//! [CellInt] may be used instead (such as `Tree<u64>`, which grows by
+//! `N<<6` each layer), to trade the height of the tree against the size of
+//! each cell.
+//!
+//! The crate is `no_std`, and only requires `alloc`. The default `std`
+//! feature adds the `std::io` helpers (such as `Tree::write_to`); build with
+//! `default-features = false` for embedded or wasm targets without `std`.
+//! The `no_std` crate in the repository builds `ksq` this way, to check
+//! that it keeps working without `std`.
 
 pub(crate) mod std {
     extern crate alloc;
@@ -43,18 +52,36 @@ pub(crate) mod std {
 
     pub use alloc::*;
     pub use core::*;
+
+    // both `alloc` and `core` export a `fmt` module.
+    pub use alloc::fmt;
+
+    #[cfg(feature = "std")]
+    extern crate std;
+
+    #[cfg(feature = "std")]
+    pub use self::std::io;
 }
 
 mod cell;
 mod layer;
 mod matrix;
 mod tree;
+mod tree_builder;
+mod tree_bytes;
 mod tree_iterator;
+mod tree_ops;
+#[cfg(feature = "rayon")]
+mod tree_rayon;
+#[cfg(feature = "serde")]
+mod tree_serde;
 
-pub(crate) use cell::Cell;
+pub use cell::{Cell, CellInt};
 pub(crate) use layer::Layer;
 pub use matrix::Matrix;
 pub use tree::{Error, Tree};
+pub use tree_builder::TreeBuilder;
+pub use tree_iterator::Iter;
 
 pub(crate) use cell::CellRepr;
 
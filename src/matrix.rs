@@ -18,11 +18,18 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE. }}}
 
-use super::Tree;
+use super::{
+    Cell, Error, Tree,
+    std::{ops::Range, vec, vec::Vec},
+};
 
 /// A [Matrix] is the user-facing 2-dimensional bit vector built on a
 /// [Tree]. The [Matrix] can store a fixed number of bits, which can be
 /// accessed using [Matrix::get], [Matrix::set] or [Matrix::unset].
+///
+/// The [Matrix] is always built on a [Tree] of the default `u16` Cells,
+/// since each layer must multiply the number of bits by a square number to
+/// keep the [Matrix] square.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Matrix(Tree);
 
@@ -38,7 +45,47 @@ impl Matrix {
         Matrix(Tree::new())
     }
 
-    /// Create a matrix from a [Tree].
+    /// Return a new [Matrix] with no bits set, which is just large enough
+    /// for [Matrix::side] to be at least `min_side`. Since each layer of the
+    /// [Tree] multiplies the side by `4`, the side will be the next power of
+    /// `4` (with a minimum of `4`). If no [Matrix] can be that large, a panic
+    /// will be triggered.
+    pub fn new_with_side(min_side: usize) -> Self {
+        let max_height = <Tree>::max_height();
+        let mut height = 1;
+        while (1 << (2 * height)) < min_side {
+            if height >= max_height {
+                panic!(
+                    "capacity out of range {} (max={})",
+                    min_side,
+                    1usize << (2 * max_height)
+                );
+            }
+            height += 1;
+        }
+        Matrix(Tree::with_height(height))
+    }
+
+    /// Return a new [Matrix] with only the provided (x, y) coordinates set,
+    /// which is just large enough to hold the largest of them (see
+    /// [Matrix::new_with_side]). The side is fixed before any of the
+    /// coordinates are mapped into the [Tree], so they always match a
+    /// later [Matrix::get].
+    pub fn from_ones(points: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let points: Vec<(usize, usize)> = points.into_iter().collect();
+        let max = points.iter().map(|(x, y)| *x.max(y)).max().unwrap_or(0);
+
+        let mut mat = Self::new_with_side(max.saturating_add(1));
+        let mut ones: Vec<usize> = points.iter().map(|(x, y)| mat.offset(*x, *y)).collect();
+        ones.sort_unstable();
+        ones.dedup();
+        mat.0 = Tree::from_sorted_ones(mat.0.height(), ones);
+        mat
+    }
+
+    /// Create a matrix from a [Tree]. A [Tree] of any height may be used,
+    /// odd or even, since every [Tree] holds a square number of bits (see
+    /// [Matrix::side]).
     pub fn from(tree: Tree) -> Self {
         Self(tree)
     }
@@ -53,41 +100,382 @@ impl Matrix {
         self.0.bits()
     }
 
+    /// Return the fraction of the bits in the Matrix which are set, from
+    /// `0.0` (no bits set) to `1.0` (every bit set).
+    pub fn density(&self) -> f64 {
+        self.0.count_ones() as f64 / self.bits() as f64
+    }
+
     /// Return the number of rows or columns in the Matrix.
+    ///
+    /// Each layer of the [Tree] multiplies the number of bits by `16`, so
+    /// a [Tree] of height `h` always holds `16^h == (4^h)^2` bits. This means
+    /// the [Matrix] is always square, with a side of `4^h`, and
+    /// `side() * side() == bits()` for every height -- odd heights included,
+    /// since `16^h` is a perfect square no matter the parity of `h`.
     pub fn side(&self) -> usize {
-        1 << ((4 * (self.0.height())) / 2)
+        1 << (2 * self.0.height())
     }
 
-    /// return the offset into the 1d tree.
+    /// return the offset into the 1d tree. If (x, y) is out of range, a panic
+    /// will be triggered, rather than wrapping around into the next row.
     fn offset(&self, x: usize, y: usize) -> usize {
-        (self.side() * y) + x
+        let side = self.side();
+        if x >= side || y >= side {
+            panic!("coordinate out of range ({}, {}) (max={})", x, y, side);
+        }
+        (side * y) + x
+    }
+
+    /// return the (x, y) coordinates of an offset into the 1d tree.
+    fn point(&self, offset: usize) -> (usize, usize) {
+        (offset % self.side(), offset / self.side())
+    }
+
+    /// Return a new [Matrix] with every bit set in either `self` or `other`
+    /// (the union of the edges, for an adjacency matrix). If the two
+    /// matrices have a different [Matrix::side], the result will have the
+    /// larger side, and the (x, y) coordinates of the smaller matrix will
+    /// be preserved.
+    pub fn or(&self, other: &Matrix) -> Matrix {
+        if self.side() == other.side() {
+            return Matrix(self.0.union(&other.0));
+        }
+
+        let (large, small) = if self.side() > other.side() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let mut ret = large.clone();
+        for offset in small.0.iter_ones() {
+            let (x, y) = small.point(offset);
+            ret.set(x, y);
+        }
+        ret
+    }
+
+    /// Return a new [Matrix] with only the bits set in both `self` and
+    /// `other` (the intersection of the edges, for an adjacency matrix). If
+    /// the two matrices have a different [Matrix::side], the result will
+    /// have the larger side, and the (x, y) coordinates of the smaller
+    /// matrix will be preserved.
+    pub fn and(&self, other: &Matrix) -> Matrix {
+        if self.side() == other.side() {
+            return Matrix(self.0.intersection(&other.0));
+        }
+
+        let (large, small) = if self.side() > other.side() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let mut ret = Matrix(Tree::with_height(large.0.height()));
+        for offset in small.0.iter_ones() {
+            let (x, y) = small.point(offset);
+            if large.0.get(large.offset(x, y)) {
+                ret.set(x, y);
+            }
+        }
+        ret
+    }
+
+    /// Iterate over the (x, y) coordinates of every set bit in the Matrix,
+    /// in the same order as the underlying [Tree::iter_ones] (row by row).
+    pub fn iter_ones(&self) -> impl Iterator<Item = (usize, usize)> {
+        let side = self.side();
+        self.0
+            .iter_ones()
+            .map(move |offset| (offset % side, offset / side))
+    }
+
+    /// Iterate over the (x, y) coordinates of every set bit within the
+    /// inclusive rectangle from (x0, y0) to (x1, y1), row by row. Any part of
+    /// the rectangle beyond the [Matrix::side] is ignored. This walks the set
+    /// bits of a single [Tree::iter_ones_range] from (x0, y0) to (x1, y1),
+    /// so the empty parts of the [Tree] are skipped entirely. If `x0 > x1` or
+    /// `y0 > y1`, a panic will be triggered.
+    pub fn iter_ones_region(
+        &self,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        if x0 > x1 || y0 > y1 {
+            panic!("region out of range ({}, {}) to ({}, {})", x0, y0, x1, y1);
+        }
+
+        let side = self.side();
+        let (x1, y1) = (x1.min(side - 1), y1.min(side - 1));
+        let range = if x0 > x1 || y0 > y1 {
+            0..0
+        } else {
+            self.offset(x0, y0)..(self.offset(x1, y1) + 1)
+        };
+
+        self.0
+            .iter_ones_range(range)
+            .map(move |offset| (offset % side, offset / side))
+            .filter(move |(x, _)| (x0..=x1).contains(x))
+    }
+
+    /// Return true if (y, x) is set for every set (x, y) (that is, the
+    /// adjacency matrix describes an undirected graph). This stops as soon as
+    /// a set (x, y) is found without a mirrored (y, x).
+    pub fn is_symmetric(&self) -> bool {
+        self.iter_ones().all(|(x, y)| x == y || self.get(y, x))
+    }
+
+    /// Set (y, x) for every set (x, y), so the Matrix is
+    /// [Matrix::is_symmetric] (making the graph undirected, for an adjacency
+    /// matrix). No bits are unset. The mirrored bits are collected first,
+    /// and then set at once with [Tree::set_many].
+    pub fn symmetrize(&mut self) {
+        let side = self.side();
+        let mirrors: Vec<usize> = self
+            .iter_ones()
+            .filter(|(x, y)| x != y)
+            .map(|(x, y)| (side * x) + y)
+            .collect();
+        self.0.set_many(&mirrors);
+    }
+
+    /// Return a dense copy of the Matrix, as [Matrix::side] rows of
+    /// [Matrix::side] bits each, so the bit at (x, y) is `dense[y][x]`. This
+    /// allocates every bit of the Matrix, so it's only suitable for small
+    /// matrices (for debugging, or handing to a dense matrix library).
+    pub fn to_dense(&self) -> Vec<Vec<bool>> {
+        let side = self.side();
+        let mut dense = vec![vec![false; side]; side];
+        for (x, y) in self.iter_ones() {
+            dense[y][x] = true;
+        }
+        dense
     }
 
-    /// Return a row of the Matrix
-    pub fn row(&self, row: usize) -> impl Iterator<Item = bool> {
-        let start = row * self.side();
-        let end = start + self.side();
-        self.0.iter_range(start..end)
+    /// Return a new [Matrix] with the same side, where every set (x, y) is
+    /// set at (y, x) instead (reversing the edges, for an adjacency matrix).
+    /// The result will not contain any empty Cells.
+    pub fn transpose(&self) -> Matrix {
+        let side = self.side();
+        let mut ones: Vec<usize> = self.iter_ones().map(|(x, y)| (side * x) + y).collect();
+        ones.sort_unstable();
+        Matrix(Tree::from_sorted_ones(self.0.height(), ones))
     }
 
-    /// Return the set 1s of the row of the Matrix
-    pub fn row_ones(&self, row: usize) -> impl Iterator<Item = usize> {
-        let start = row * self.side();
-        let end = start + self.side();
-        self.0.iter_ones_range(start..end).map(move |v| v - start)
+    /// Return a row of the Matrix. If the row is out of range, a panic will
+    /// be triggered.
+    pub fn row(&self, y: usize) -> impl Iterator<Item = bool> {
+        self.0.iter_range(self.row_range(y))
     }
 
-    /// return the value of the bit at (x, y)
-    pub fn get(&mut self, x: usize, y: usize) -> bool {
+    /// Return the x coordinates of the set 1s in row `y` of the Matrix (the
+    /// out-neighbors of node `y`, for an adjacency matrix). If the row is
+    /// out of range, a panic will be triggered.
+    pub fn row_ones(&self, y: usize) -> impl Iterator<Item = usize> {
+        let range = self.row_range(y);
+        let start = range.start;
+        self.0.iter_ones_range(range).map(move |v| v - start)
+    }
+
+    /// Return the y coordinates of the set 1s in column `x` of the Matrix
+    /// (the in-neighbors of node `x`, for an adjacency matrix). Columns are
+    /// not contiguous in the underlying [Tree], so this walks each of the
+    /// leaf Cells, skipping over the empty parts of the [Tree] entirely. If
+    /// the column is out of range, a panic will be triggered.
+    pub fn col_ones(&self, x: usize) -> impl Iterator<Item = usize> {
+        let side = self.side();
+        if x >= side {
+            panic!("column out of range {} (max={})", x, side);
+        }
+
+        self.0
+            .leaf_layer()
+            .into_iter()
+            .flat_map(move |(offset, cell)| {
+                let end = offset + Cell::<u16>::bits();
+                (offset / side..end.div_ceil(side)).filter(move |y| {
+                    let bit = (side * y) + x;
+                    bit >= offset && bit < end && cell.get(bit - offset)
+                })
+            })
+    }
+
+    /// Return each `i` where (i, i) is set in the Matrix (the self-loops,
+    /// for an adjacency matrix), in ascending order. Like
+    /// [Matrix::col_ones], this walks each of the leaf Cells, skipping over
+    /// the empty parts of the [Tree] entirely.
+    pub fn diagonal_ones(&self) -> impl Iterator<Item = usize> {
+        let stride = self.side() + 1;
+
+        self.0
+            .leaf_layer()
+            .into_iter()
+            .flat_map(move |(offset, cell)| {
+                let end = offset + Cell::<u16>::bits();
+                (offset.div_ceil(stride)..end.div_ceil(stride))
+                    .filter(move |i| cell.get((stride * i) - offset))
+            })
+    }
+
+    /// Return the number of set bits in row `y` of the Matrix (the
+    /// out-degree of node `y`, for an adjacency matrix), using
+    /// [Tree::count_ones_range]. If the row is out of range, a panic will be
+    /// triggered.
+    pub fn count_row_ones(&self, y: usize) -> usize {
+        self.0.count_ones_range(self.row_range(y))
+    }
+
+    /// Return the number of set bits in column `x` of the Matrix (the
+    /// in-degree of node `x`, for an adjacency matrix), by counting
+    /// [Matrix::col_ones]. If the column is out of range, a panic will be
+    /// triggered.
+    pub fn count_col_ones(&self, x: usize) -> usize {
+        self.col_ones(x).count()
+    }
+
+    /// return the range of offsets into the 1d tree covered by row `y`.
+    fn row_range(&self, y: usize) -> Range<usize> {
+        let side = self.side();
+        if y >= side {
+            panic!("row out of range {} (max={})", y, side);
+        }
+        let start = side * y;
+        start..(start + side)
+    }
+
+    /// Return the coordinates of the set bits orthogonally adjacent to
+    /// (x, y) -- above, to the left, to the right and below, in that order.
+    /// Neighbors beyond the edges of the [Matrix] are skipped, rather than
+    /// wrapping around. If (x, y) is out of range, a panic will be
+    /// triggered.
+    pub fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let side = self.side();
+        if x >= side || y >= side {
+            panic!("coordinate out of range ({}, {}) (max={})", x, y, side);
+        }
+
+        [
+            y.checked_sub(1).map(|y| (x, y)),
+            x.checked_sub(1).map(|x| (x, y)),
+            Some((x + 1, y)).filter(|_| x + 1 < side),
+            Some((x, y + 1)).filter(|_| y + 1 < side),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(move |(x, y)| self.get(*x, *y))
+    }
+
+    /// Treating the [Matrix] as an adjacency matrix (where row `y` holds
+    /// the nodes reachable from `y`), return the number of hops from `start`
+    /// to every node, or `None` if the node can not be reached. The
+    /// distance from `start` to itself is always `0`. If `start` is out of
+    /// range, a panic will be triggered.
+    pub fn bfs_distances(&self, start: usize) -> Vec<Option<usize>> {
+        let side = self.side();
+        if start >= side {
+            panic!("node out of range {} (max={})", start, side);
+        }
+
+        let mut visited = Tree::with_capacity_bits(side);
+
+        let mut distances = vec![None; side];
+        let mut frontier = vec![start];
+        let mut distance = 0;
+
+        visited.set(start);
+        while !frontier.is_empty() {
+            let mut next = vec![];
+            for node in frontier {
+                distances[node] = Some(distance);
+                for neighbor in self.row_ones(node) {
+                    if !visited.get(neighbor) {
+                        visited.set(neighbor);
+                        next.push(neighbor);
+                    }
+                }
+            }
+            frontier = next;
+            distance += 1;
+        }
+
+        distances
+    }
+
+    /// return the value of the bit at (x, y). If either of the coordinates is
+    /// outside of the [Matrix::side], a panic will be triggered.
+    pub fn get(&self, x: usize, y: usize) -> bool {
         self.0.get(self.offset(x, y))
     }
 
-    /// set the value of the bit at (x, y)
+    /// set the value of the bit at (x, y). If either of the coordinates is
+    /// outside of the [Matrix::side], a panic will be triggered.
     pub fn set(&mut self, x: usize, y: usize) {
         self.0.set(self.offset(x, y));
     }
 
-    /// unset the value of the bit at (x, y)
+    /// return the value of the bit at (x, y), or `None` if either of the
+    /// coordinates is outside of the [Matrix::side].
+    pub fn try_get(&self, x: usize, y: usize) -> Option<bool> {
+        self.check_bounds(x, y).ok()?;
+        Some(self.0.get(self.offset(x, y)))
+    }
+
+    /// return the value of the bit at (x, y), or `None` if either of the
+    /// coordinates is outside of the [Matrix::side]. This is the same as
+    /// [Matrix::try_get], matching [Tree::get_checked].
+    pub fn get_checked(&self, x: usize, y: usize) -> Option<bool> {
+        self.try_get(x, y)
+    }
+
+    /// set the value of the bit at (x, y), returning an [Error::OutOfRange]
+    /// rather than panicking if either of the coordinates is outside of the
+    /// [Matrix::side].
+    pub fn try_set(&mut self, x: usize, y: usize) -> Result<(), Error> {
+        self.check_bounds(x, y)?;
+        self.0.set(self.offset(x, y));
+        Ok(())
+    }
+
+    /// Check that both coordinates are within the [Matrix::side].
+    fn check_bounds(&self, x: usize, y: usize) -> Result<(), Error> {
+        let max = self.side();
+        for bit in [x, y] {
+            if bit >= max {
+                return Err(Error::OutOfRange { bit, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// set every bit in the inclusive rectangle from (x0, y0) to (x1, y1).
+    /// If the rectangle is beyond the [Matrix::side], the [Matrix] is first
+    /// grown (like [Matrix::or], keeping the (x, y) coordinates of the bits
+    /// already set) until it fits. Each row of the rectangle is contiguous
+    /// in the underlying [Tree], so the whole rectangle is set at once with
+    /// [Tree::set_many]. If `x0 > x1` or `y0 > y1`, a panic will be
+    /// triggered.
+    pub fn set_region(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x0 > x1 || y0 > y1 {
+            panic!("region out of range ({}, {}) to ({}, {})", x0, y0, x1, y1);
+        }
+
+        let max = x1.max(y1);
+        if max >= self.side() {
+            *self = self.or(&Self::new_with_side(max.saturating_add(1)));
+        }
+
+        let bits: Vec<usize> = (y0..=y1)
+            .flat_map(|y| self.offset(x0, y)..=self.offset(x1, y))
+            .collect();
+        self.0.set_many(&bits);
+    }
+
+    /// unset the value of the bit at (x, y). If either of the coordinates is
+    /// outside of the [Matrix::side], a panic will be triggered.
     pub fn unset(&mut self, x: usize, y: usize) {
         self.0.unset(self.offset(x, y));
     }
@@ -102,7 +490,6 @@ impl Matrix {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::std::{vec, vec::Vec};
 
     #[test]
     fn matrix_dimensions() {
@@ -119,6 +506,98 @@ mod tests {
         assert_eq!(64, mat.side());
     }
 
+    #[test]
+    fn matrix_new_with_side() {
+        assert_eq!(4, Matrix::new_with_side(0).side());
+        assert_eq!(4, Matrix::new_with_side(4).side());
+        assert_eq!(16, Matrix::new_with_side(5).side());
+        assert_eq!(64, Matrix::new_with_side(60).side());
+        assert_eq!(64, Matrix::new_with_side(64).side());
+        assert_eq!(256, Matrix::new_with_side(65).side());
+        assert_eq!(Matrix::new(), Matrix::new_with_side(1));
+
+        let mut mat = Matrix::new_with_side(64);
+        mat.set(63, 63);
+        assert!(mat.get(63, 63));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn matrix_new_with_side_overflow() {
+        Matrix::new_with_side(usize::MAX);
+    }
+
+    #[test]
+    fn matrix_from_ones() {
+        let points = vec![(3, 0), (1, 1), (9, 2), (0, 7), (60, 3), (1, 1)];
+        let mat = Matrix::from_ones(points.clone());
+        assert_eq!(64, mat.side());
+
+        let mut expected = points.clone();
+        expected.sort_by_key(|(x, y)| (*y, *x));
+        expected.dedup();
+        assert_eq!(expected, mat.iter_ones().collect::<Vec<_>>());
+        for (x, y) in points {
+            assert!(mat.get(x, y));
+        }
+
+        assert_eq!(Matrix::new(), Matrix::from_ones([]));
+        assert_eq!(4, Matrix::from_ones([(3, 3)]).side());
+        assert_eq!(16, Matrix::from_ones([(0, 4)]).side());
+    }
+
+    #[test]
+    fn matrix_density() {
+        let mut mat = Matrix::new();
+        mat.grow();
+        assert_eq!(0.0, mat.density());
+        assert_eq!(0.0, Matrix::new().density());
+
+        for y in 0..4 {
+            for x in 0..mat.side() {
+                mat.set(x, y);
+            }
+        }
+        assert!((mat.density() - 0.25).abs() < f64::EPSILON);
+
+        for y in 0..mat.side() {
+            for x in 0..mat.side() {
+                mat.set(x, y);
+            }
+        }
+        assert_eq!(1.0, mat.density());
+    }
+
+    #[test]
+    fn matrix_side_square() {
+        let mut mat = Matrix::new();
+        for height in 1..=8 {
+            assert_eq!(height, mat.clone().into_inner().height());
+            assert_eq!(1 << (2 * height), mat.side());
+            assert_eq!(mat.bits(), mat.side() * mat.side());
+            mat.grow();
+        }
+    }
+
+    #[test]
+    fn matrix_from_odd_height() {
+        let mut tree = Tree::with_height(3);
+        tree.set(4095);
+        let mut mat = Matrix::from(tree);
+
+        assert_eq!(64, mat.side());
+        assert_eq!(mat.bits(), mat.side() * mat.side());
+        assert!(mat.get(63, 63));
+        assert_eq!(vec![(63, 63)], mat.iter_ones().collect::<Vec<_>>());
+
+        mat.set(63, 0);
+        mat.set(0, 63);
+        assert_eq!(
+            vec![63, 4032, 4095],
+            mat.into_inner().iter_ones().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn matrix_get_set_xy() {
         let mut mat = Matrix::new();
@@ -157,6 +636,481 @@ mod tests {
         }
     }
 
+    #[test]
+    fn matrix_try_get_set() {
+        let mut mat = Matrix::new();
+        mat.grow();
+
+        let side = mat.side();
+        assert_eq!(Some(false), mat.try_get(side - 1, side - 1));
+        assert_eq!(Ok(()), mat.try_set(side - 1, side - 1));
+        assert_eq!(Some(true), mat.try_get(side - 1, side - 1));
+
+        assert_eq!(None, mat.try_get(side, 0));
+        assert_eq!(None, mat.try_get(0, side));
+        assert_eq!(
+            Err(Error::OutOfRange {
+                bit: side,
+                max: side
+            }),
+            mat.try_set(side, 0)
+        );
+        assert_eq!(
+            Err(Error::OutOfRange {
+                bit: side + 3,
+                max: side
+            }),
+            mat.try_set(0, side + 3)
+        );
+
+        // nothing aliased into (0, 1) from the rejected (side, 0).
+        assert!(!mat.get(0, 1));
+    }
+
+    #[test]
+    fn matrix_set_region() {
+        let mut mat = Matrix::new();
+        mat.set_region(1, 1, 3, 3);
+
+        assert_eq!(4, mat.side());
+        assert_eq!(9, mat.0.count_ones());
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(x >= 1 && y >= 1, mat.get(x, y), "({}, {})", x, y);
+            }
+        }
+
+        let mut single = Matrix::new();
+        single.set_region(2, 3, 2, 3);
+        assert_eq!(vec![(2, 3)], single.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn matrix_set_region_grow() {
+        let mut mat = Matrix::new();
+        mat.set(0, 3);
+        mat.set_region(2, 4, 5, 6);
+
+        assert_eq!(16, mat.side());
+        assert_eq!(13, mat.0.count_ones());
+        assert!(mat.get(0, 3));
+        for y in 0..16 {
+            for x in 0..16 {
+                let inside = (2..=5).contains(&x) && (4..=6).contains(&y);
+                assert_eq!(inside || (x, y) == (0, 3), mat.get(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "region out of range")]
+    fn matrix_set_region_reversed() {
+        Matrix::new().set_region(2, 0, 1, 0);
+    }
+
+    #[test]
+    fn matrix_get_checked() {
+        let mut mat = Matrix::new();
+        mat.set(0, 1);
+        mat.set(3, 3);
+
+        assert_eq!(Some(true), mat.get_checked(0, 1));
+        assert_eq!(Some(true), mat.get_checked(3, 3));
+        assert_eq!(Some(false), mat.get_checked(1, 0));
+
+        // (side, 0) would alias (0, 1), if it wasn't bounds checked.
+        assert_eq!(None, mat.get_checked(mat.side(), 0));
+        assert_eq!(None, mat.get_checked(0, mat.side()));
+        assert_eq!(None, mat.get_checked(usize::MAX, usize::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "coordinate out of range")]
+    fn matrix_get_wrapped() {
+        let mut mat = Matrix::new();
+        mat.set(0, 1);
+        mat.get(mat.side(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "coordinate out of range")]
+    fn matrix_set_wrapped() {
+        let mut mat = Matrix::new();
+        mat.set(mat.side() + 1, 0);
+    }
+
+    #[test]
+    fn matrix_bfs_distances() {
+        let mut mat = Matrix::new();
+
+        // 0 -> 2 -> 4 -> 6, 0 -> 4, and 6 -> 0; nothing points to 8
+        // (mat.side() == 16 after the first grow).
+        mat.grow();
+        mat.set(2, 0);
+        mat.set(4, 0);
+        mat.set(4, 2);
+        mat.set(6, 4);
+        mat.set(0, 6);
+        mat.set(10, 8);
+
+        let distances = mat.bfs_distances(0);
+        assert_eq!(16, distances.len());
+        assert_eq!(Some(0), distances[0]);
+        assert_eq!(Some(1), distances[2]);
+        assert_eq!(Some(1), distances[4]);
+        assert_eq!(Some(2), distances[6]);
+        assert_eq!(None, distances[8]);
+        assert_eq!(None, distances[10]);
+
+        let distances = mat.bfs_distances(8);
+        assert_eq!(Some(0), distances[8]);
+        assert_eq!(Some(1), distances[10]);
+        assert_eq!(None, distances[0]);
+    }
+
+    #[test]
+    fn matrix_or_and() {
+        let mut small = Matrix::new();
+        small.set(1, 0);
+        small.set(3, 2);
+        small.set(0, 3);
+
+        let mut large = Matrix::new();
+        large.grow();
+        large.set(3, 2);
+        large.set(0, 3);
+        large.set(15, 15);
+        large.set(4, 0);
+
+        let ones = |mat: &Matrix| {
+            mat.0
+                .iter_ones()
+                .map(|offset| mat.point(offset))
+                .collect::<Vec<_>>()
+        };
+
+        let or = small.or(&large);
+        assert_eq!(16, or.side());
+        assert_eq!(vec![(1, 0), (4, 0), (3, 2), (0, 3), (15, 15)], ones(&or));
+        assert_eq!(or, large.or(&small));
+
+        let and = small.and(&large);
+        assert_eq!(16, and.side());
+        assert_eq!(vec![(3, 2), (0, 3)], ones(&and));
+        assert_eq!(and, large.and(&small));
+
+        let mut same = Matrix::new();
+        same.set(3, 2);
+        same.set(2, 2);
+        assert_eq!(vec![(1, 0), (2, 2), (3, 2), (0, 3)], ones(&small.or(&same)));
+        assert_eq!(vec![(3, 2)], ones(&small.and(&same)));
+        assert_eq!(small.0.union(&same.0), small.or(&same).0);
+        assert_eq!(small.0.intersection(&same.0), small.and(&same).0);
+
+        assert_eq!(small, small.or(&Matrix::new()));
+        assert_eq!(None, ones(&small.and(&Matrix::new())).first());
+    }
+
+    #[test]
+    fn matrix_iter_ones() {
+        let mut mat = Matrix::new();
+        mat.grow();
+
+        let mut points = vec![(3, 0), (15, 15), (0, 7), (9, 2), (1, 1)];
+        for (x, y) in points.iter() {
+            mat.set(*x, *y);
+        }
+
+        let mut ones = mat.iter_ones().collect::<Vec<_>>();
+        ones.sort();
+        points.sort();
+        assert_eq!(points, ones);
+
+        // growing changes the side, so the coordinates move, but they must
+        // always agree with `get`.
+        mat.grow();
+        mat.grow();
+        let ones = mat.iter_ones().collect::<Vec<_>>();
+        assert_eq!(points.len(), ones.len());
+        for (x, y) in ones {
+            assert!(x < mat.side() && y < mat.side());
+            assert!(mat.get(x, y));
+        }
+    }
+
+    #[test]
+    fn matrix_row_ones() {
+        let mut mat = Matrix::new();
+        mat.grow();
+
+        mat.set(1, 5);
+        mat.set(15, 5);
+        mat.set(0, 7);
+        mat.set(15, 15);
+
+        assert_eq!(vec![1, 15], mat.row_ones(5).collect::<Vec<_>>());
+        assert_eq!(vec![0], mat.row_ones(7).collect::<Vec<_>>());
+        assert_eq!(vec![15], mat.row_ones(15).collect::<Vec<_>>());
+        assert_eq!(None, mat.row_ones(0).next());
+    }
+
+    #[test]
+    #[should_panic(expected = "row out of range")]
+    fn matrix_row_ones_out_of_range() {
+        let _ = Matrix::new().row_ones(4);
+    }
+
+    #[test]
+    fn matrix_col_ones() {
+        let mut mat = Matrix::new();
+        mat.grow();
+        mat.grow();
+
+        mat.set(5, 0);
+        mat.set(5, 3);
+        mat.set(5, 40);
+        mat.set(5, 63);
+        mat.set(4, 3);
+        mat.set(6, 40);
+        mat.set(63, 63);
+
+        assert_eq!(vec![0, 3, 40, 63], mat.col_ones(5).collect::<Vec<_>>());
+        assert_eq!(vec![3], mat.col_ones(4).collect::<Vec<_>>());
+        assert_eq!(vec![63], mat.col_ones(63).collect::<Vec<_>>());
+        assert_eq!(None, mat.col_ones(0).next());
+
+        let mut mat = Matrix::new();
+        mat.set(2, 0);
+        mat.set(2, 3);
+        mat.set(1, 3);
+        assert_eq!(vec![0, 3], mat.col_ones(2).collect::<Vec<_>>());
+        assert_eq!(vec![3], mat.col_ones(1).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "column out of range")]
+    fn matrix_col_ones_out_of_range() {
+        let _ = Matrix::new().col_ones(4);
+    }
+
+    #[test]
+    fn matrix_iter_ones_region() {
+        let mut mat = Matrix::new_with_side(16);
+        for y in 0..16 {
+            for x in 0..16 {
+                if (x * 7 + y * 3) % 5 == 0 {
+                    mat.set(x, y);
+                }
+            }
+        }
+
+        for (x0, y0, x1, y1) in [(0, 0, 15, 15), (2, 3, 5, 9), (7, 7, 7, 7), (10, 12, 40, 40)] {
+            let expected: Vec<(usize, usize)> = mat
+                .iter_ones()
+                .filter(|(x, y)| (x0..=x1).contains(x) && (y0..=y1).contains(y))
+                .collect();
+            assert_eq!(
+                expected,
+                mat.iter_ones_region(x0, y0, x1, y1).collect::<Vec<_>>()
+            );
+        }
+
+        assert_eq!(0, mat.iter_ones_region(16, 0, 20, 20).count());
+        assert_eq!(
+            vec![(2, 3), (5, 3)],
+            Matrix::from_ones([(1, 3), (2, 3), (5, 3), (6, 3), (2, 4)])
+                .iter_ones_region(2, 3, 5, 3)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "region out of range")]
+    fn matrix_iter_ones_region_reversed() {
+        let _ = Matrix::new().iter_ones_region(0, 2, 0, 1);
+    }
+
+    #[test]
+    fn matrix_is_symmetric() {
+        let mut mat = Matrix::from_ones([(1, 0), (0, 1), (2, 2), (3, 1), (1, 3)]);
+        assert!(mat.is_symmetric());
+        assert!(Matrix::new().is_symmetric());
+
+        mat.set(3, 2);
+        assert!(!mat.is_symmetric());
+        mat.set(2, 3);
+        assert!(mat.is_symmetric());
+
+        mat.unset(0, 1);
+        assert!(!mat.is_symmetric());
+    }
+
+    #[test]
+    fn matrix_symmetrize() {
+        let mut mat = Matrix::from_ones([(1, 0), (2, 2), (3, 1), (1, 3), (15, 4)]);
+        let before: Vec<(usize, usize)> = mat.iter_ones().collect();
+        assert!(!mat.is_symmetric());
+
+        mat.symmetrize();
+        assert!(mat.is_symmetric());
+        for (x, y) in before {
+            assert!(mat.get(x, y));
+            assert!(mat.get(y, x));
+        }
+        assert_eq!(7, mat.0.count_ones());
+
+        let symmetric = mat.clone();
+        mat.symmetrize();
+        assert_eq!(symmetric, mat);
+    }
+
+    #[test]
+    fn matrix_to_dense() {
+        let mut mat = Matrix::new();
+        mat.set(1, 0);
+        mat.set(3, 2);
+        mat.set(0, 3);
+
+        assert_eq!(
+            vec![
+                vec![false, true, false, false],
+                vec![false, false, false, false],
+                vec![false, false, false, true],
+                vec![true, false, false, false],
+            ],
+            mat.to_dense()
+        );
+
+        let mut mat = Matrix::new_with_side(16);
+        mat.set(15, 9);
+        let dense = mat.to_dense();
+        assert_eq!(16, dense.len());
+        assert!(dense.iter().all(|row| row.len() == 16));
+        assert!(dense[9][15]);
+        assert_eq!(1, dense.iter().flatten().filter(|bit| **bit).count());
+    }
+
+    #[test]
+    fn matrix_diagonal_ones() {
+        let mut mat = Matrix::new_with_side(64);
+        for i in [0, 5, 17, 63] {
+            mat.set(i, i);
+        }
+        mat.set(1, 0);
+        mat.set(4, 5);
+        mat.set(63, 62);
+
+        assert_eq!(vec![0, 5, 17, 63], mat.diagonal_ones().collect::<Vec<_>>());
+        assert_eq!(
+            (0..64).filter(|i| mat.get(*i, *i)).collect::<Vec<_>>(),
+            mat.diagonal_ones().collect::<Vec<_>>()
+        );
+
+        let mut mat = Matrix::new();
+        assert_eq!(0, mat.diagonal_ones().count());
+        mat.set(3, 3);
+        mat.set(2, 3);
+        assert_eq!(vec![3], mat.diagonal_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn matrix_count_row_col_ones() {
+        // 0 -> 1, 0 -> 2, 1 -> 2, 3 -> 2, 3 -> 0
+        let mat = Matrix::from_ones([(1, 0), (2, 0), (2, 1), (2, 3), (0, 3)]);
+
+        assert_eq!(
+            vec![2, 1, 0, 2],
+            (0..4).map(|y| mat.count_row_ones(y)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 1, 3, 0],
+            (0..4).map(|x| mat.count_col_ones(x)).collect::<Vec<_>>()
+        );
+
+        let empty = Matrix::new_with_side(16);
+        assert_eq!(0, empty.count_row_ones(15));
+        assert_eq!(0, empty.count_col_ones(15));
+    }
+
+    #[test]
+    #[should_panic(expected = "row out of range")]
+    fn matrix_count_row_ones_out_of_range() {
+        Matrix::new().count_row_ones(4);
+    }
+
+    #[test]
+    #[should_panic(expected = "column out of range")]
+    fn matrix_count_col_ones_out_of_range() {
+        Matrix::new().count_col_ones(4);
+    }
+
+    #[test]
+    fn matrix_transpose() {
+        let mut mat = Matrix::new();
+        mat.grow();
+        mat.set(1, 0);
+        mat.set(15, 2);
+        mat.set(3, 3);
+        mat.set(0, 9);
+
+        let transposed = mat.transpose();
+        assert_eq!(mat.side(), transposed.side());
+        for (x, y) in [(0, 1), (2, 15), (3, 3), (9, 0)] {
+            assert!(transposed.get(x, y));
+        }
+        assert!(!transposed.get(1, 0));
+        assert_eq!(4, transposed.iter_ones().count());
+
+        let mut pruned = mat.clone().into_inner();
+        pruned.prune();
+        assert_eq!(Matrix::from(pruned), transposed.transpose());
+        assert_eq!(transposed.0.compact_to_vec(), transposed.0.to_vec());
+    }
+
+    #[test]
+    fn matrix_get_shared() {
+        let mut mat = Matrix::new();
+        mat.set(1, 2);
+
+        let shared: &Matrix = &mat;
+        assert!(shared.get(1, 2));
+        assert!(!shared.get(2, 1));
+    }
+
+    #[test]
+    fn matrix_neighbors() {
+        let mut mat = Matrix::new();
+        mat.grow();
+
+        // a plus around (5, 5), with the center unset.
+        for (x, y) in [(5, 4), (4, 5), (6, 5), (5, 6)] {
+            mat.set(x, y);
+        }
+        assert_eq!(
+            vec![(5, 4), (4, 5), (6, 5), (5, 6)],
+            mat.neighbors(5, 5).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(5, 4), (4, 5)],
+            mat.neighbors(4, 4).collect::<Vec<_>>()
+        );
+        assert_eq!(None, mat.neighbors(10, 10).next());
+
+        // corners and edges must not wrap.
+        mat.set(15, 0);
+        mat.set(0, 1);
+        mat.set(1, 15);
+        assert_eq!(vec![(0, 1)], mat.neighbors(0, 0).collect::<Vec<_>>());
+        assert_eq!(vec![(15, 0)], mat.neighbors(15, 1).collect::<Vec<_>>());
+        assert_eq!(vec![(1, 15)], mat.neighbors(0, 15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "coordinate out of range")]
+    fn matrix_neighbors_out_of_range() {
+        let _ = Matrix::new().neighbors(0, 4);
+    }
+
     #[test]
     fn matrix_get_row() {
         let mut mat = Matrix::new();
@@ -175,6 +1129,27 @@ mod tests {
             row
         );
     }
+
+    #[test]
+    fn matrix_row_after_set_row() {
+        // each row of a Matrix with a side of 16 is a single leaf Cell, so
+        // every row starts exactly where the Cell of the row above ends.
+        let mut mat = Matrix::new();
+        mat.grow();
+
+        mat.set(2, 0);
+        mat.set(4, 1);
+        mat.set(0, 2);
+
+        assert_eq!(vec![4], mat.row_ones(1).collect::<Vec<_>>());
+        assert_eq!(vec![0], mat.row_ones(2).collect::<Vec<_>>());
+
+        for (y, x) in [(1, 4), (2, 0)] {
+            let row = mat.row(y).collect::<Vec<_>>();
+            assert_eq!(16, row.len());
+            assert_eq!(vec![x], (0..16).filter(|x| row[*x]).collect::<Vec<_>>());
+        }
+    }
 }
 
 // vim: foldmethod=marker
@@ -19,18 +19,68 @@
 // THE SOFTWARE. }}}
 
 use super::{
-    Cell, CellRepr, CountOnes, Layer,
-    std::{vec, vec::Vec},
+    Cell, CellInt, CellRepr, CountOnes, Layer,
+    std::{
+        fmt::Write,
+        hash::{Hash, Hasher},
+        mem::{self, size_of},
+        ops::Range,
+        string::String,
+        vec,
+        vec::Vec,
+    },
 };
 
 /// A `tree` is the user-facing 1-dimensional bit vector. The `tree` can store
 /// a fixed number of bits, which can be accessed using [Tree::get],
 /// [Tree::set] or maybe [Tree::unset]
-#[derive(Debug, Clone, PartialEq)]
-pub struct Tree(Vec<Layer>);
+///
+/// Each Cell of the `tree` is backed by a `u16` by default, but any
+/// [CellInt] may be used instead (for instance, `Tree<u64>`). The
+/// constructors which can't infer the [CellInt] from their arguments (such
+/// as [Tree::new]) only exist for the default `u16`; [Default],
+/// [FromIterator] and [Tree::from_cells] work for every [CellInt]. The
+/// byte encodings (such as [Tree::to_bytes_with_len]) also only exist for
+/// the default `u16`, so their format never depends on the [CellInt].
+///
+/// Two trees are `==` only if they have exactly the same Cells (see
+/// [Tree::semantically_eq] to compare only the set bits). [Hash] only
+/// depends on the set bits, so trees which are `==` always hash the same,
+/// as do trees which are [Tree::semantically_eq]. To deduplicate sets of
+/// bits using trees as keys, [Tree::prune] trees of the same height first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Tree<T: CellInt = CellRepr>(Vec<Layer<T>>);
+
+impl<T: CellInt> Hash for Tree<T> {
+    /// Hash the offset and value of each of the non-empty leaf Cells, so the
+    /// hash ignores the height of the [Tree], and any empty Cells left
+    /// behind by [Tree::unset].
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (offset, cell) in self.leaf_layer() {
+            if cell.inner() != T::ZERO {
+                offset.hash(state);
+                cell.inner().hash(state);
+            }
+        }
+    }
+}
+
+impl<T: CellInt> Clone for Tree<T> {
+    fn clone(&self) -> Self {
+        Tree(self.0.clone())
+    }
+
+    /// Copy `source` into this [Tree], re-using the allocation of each of the
+    /// existing layers where possible, rather than re-allocating every layer
+    /// like `*self = source.clone()` would. This is useful for repeatedly
+    /// resetting a scratch [Tree] to a snapshot.
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+    }
+}
 
 /// Possible error types which may be returned by the [Tree] during
-/// construction.
+/// construction, or by the fallible accessors.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Error {
     /// The bytewise encoding of the K2 [Tree] is malformed -- usually this
@@ -39,26 +89,155 @@ pub enum Error {
 
     /// No data was provided, so no [Tree] can be constructed.
     Empty,
+
+    /// The [Tree] can not be shrunk without dropping set bits, or is
+    /// already as small as it can be.
+    CannotShrink,
+
+    /// Reading the [Tree] from an I/O source failed.
+    Io,
+
+    /// The requested bit (or [crate::Matrix] coordinate) is outside of the
+    /// addressable range.
+    OutOfRange {
+        /// Requested bit offset or coordinate.
+        bit: usize,
+
+        /// Exclusive upper bound for `bit`.
+        max: usize,
+    },
 }
 
-impl Default for Tree {
+impl<T: CellInt> Default for Tree<T> {
+    /// Create a new [Tree] with the smallest possible capacity, a single
+    /// Cell. See [Tree::new].
     fn default() -> Self {
-        Self::new()
+        Tree(vec![Layer(vec![Cell::new()])])
+    }
+}
+
+impl<T: CellInt> FromIterator<usize> for Tree<T> {
+    /// Create a new [Tree] with only the collected bits set, just tall
+    /// enough to hold the largest of them. See [Tree::from_ones].
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut indices: Vec<usize> = iter.into_iter().collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let max = indices.last().copied().unwrap_or(0);
+        Self::from_sorted_ones(Self::min_height(max), indices)
+    }
+}
+
+impl<T: CellInt> Extend<usize> for Tree<T> {
+    /// Set each of the provided bits, calling [Tree::grow_to] whenever a bit
+    /// is beyond the current capacity of the [Tree]. If a bit is beyond the
+    /// largest capacity representable by a `usize`, a panic will be
+    /// triggered.
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for bit in iter {
+            let min_bits = bit.checked_add(1).unwrap_or_else(|| {
+                panic!("capacity out of range {} (max={})", bit, Self::max_bits())
+            });
+            self.grow_to(min_bits);
+            self.set(bit);
+        }
     }
 }
 
 impl Tree {
-    /// Create a new [Tree] with the smallest possible capacity, `16` bits,
-    /// in this case. You may [Tree::grow] to increase the bit capacity of
-    /// the [Tree].
+    /// Create a new [Tree] with the smallest possible capacity, a single
+    /// `u16` Cell (`16` bits). You may [Tree::grow] to increase the bit
+    /// capacity of the [Tree]. For other [CellInt] types, use
+    /// [Default::default].
     pub fn new() -> Self {
-        Tree(vec![Layer(vec![0.into()])])
+        Self::default()
+    }
+
+    /// Create a new [Tree] with no bits set, which is exactly `height`
+    /// layers tall (with a minimum of `1`). If the [Tree::bits] of a [Tree]
+    /// that tall would not be representable by a `usize`, a panic will be
+    /// triggered.
+    pub fn with_height(height: usize) -> Self {
+        Self::bits_for(height);
+        Self::from_sorted_ones(height.max(1), [])
+    }
+
+    /// Create a new [Tree] with no bits set, which is just tall enough for
+    /// [Tree::bits] to be at least `min_bits` (with a minimum of one layer).
+    /// Like [Tree::with_height], no bits are set in any layer. If `min_bits`
+    /// is beyond the largest capacity representable by a `usize` (see
+    /// [Tree::grow_to]), a panic will be triggered.
+    pub fn with_capacity_bits(min_bits: usize) -> Self {
+        Self::with_height(Self::min_height(min_bits.saturating_sub(1)))
+    }
+
+    /// Create a new [Tree] with only the provided bits set, which is just
+    /// tall enough to hold the largest of them. The bits may be provided in
+    /// any order, and are sorted before building each layer in order, rather
+    /// than [Tree::set]-ing each bit from the root. If the largest bit is
+    /// beyond the largest capacity representable by a `usize` (see
+    /// [Tree::grow_to]), a panic will be triggered.
+    ///
+    /// For other [CellInt] types, [Iterator::collect] the bits instead.
+    pub fn from_ones(indices: impl IntoIterator<Item = usize>) -> Self {
+        indices.into_iter().collect()
+    }
+
+    /// Create a new [Tree] from a dense slice of bits, just tall enough to
+    /// hold `bits.len()` bits, with the bit at each `true` position set. This
+    /// is the inverse of collecting [Tree::iter]; only the set bits are
+    /// visited while building the layers, so a mostly-`false` slice results
+    /// in a sparse [Tree].
+    pub fn from_bits(bits: &[bool]) -> Self {
+        Self::from_sorted_ones(
+            Self::min_height(bits.len().saturating_sub(1)),
+            bits.iter()
+                .enumerate()
+                .filter(|(_, bit)| **bit)
+                .map(|(idx, _)| idx),
+        )
     }
 
     /// Construct a new K2 [Tree] from a set of `u16` "Cells". The bytewise
     /// encoding of the K2 [Tree] is self-describing, so no additional data
     /// beyond the underlying values is required.
+    ///
+    /// Every Cell must be consumed by the structure of the [Tree]: any Cells
+    /// left over after a layer with no bits set, or too few Cells to fill
+    /// the next layer, are rejected with an [Error::Malformed].
     pub fn from(v: &[CellRepr]) -> Result<Self, Error> {
+        Self::from_cells(v)
+    }
+
+    /// Return the height of the smallest [Tree] which can hold `max_bit`
+    /// (that is, whose [Tree::bits] is larger than `max_bit`), without
+    /// building one. If `max_bit` is beyond the largest capacity
+    /// representable by a `usize` (see [Tree::grow_to]), a panic will be
+    /// triggered.
+    pub fn height_for(max_bit: usize) -> usize {
+        Self::min_height(max_bit)
+    }
+
+    /// Return the [Tree::bits] of a [Tree] which is `height` layers tall,
+    /// without building one. If `height` is too tall for the [Tree::bits]
+    /// to be representable by a `usize`, a panic will be triggered.
+    pub fn bits_for(height: usize) -> usize {
+        if height > Self::max_height() {
+            panic!(
+                "capacity out of range {} (max={})",
+                height,
+                Self::max_height()
+            );
+        }
+        Self::layer_shift(height)
+    }
+}
+
+impl<T: CellInt> Tree<T> {
+    /// Construct a new K2 [Tree] from a set of "Cells" of any [CellInt],
+    /// like [Tree::from].
+    pub fn from_cells(v: &[T]) -> Result<Self, Error> {
         if v.is_empty() {
             return Err(Error::Empty);
         }
@@ -72,11 +251,10 @@ impl Tree {
         while !v.is_empty() {
             let layer_len = tree[tree.len() - 1].count_ones();
 
-            if layer_len == 0 && !v.is_empty() {
-                return Err(Error::Malformed);
-            }
-
-            if layer_len > v.len() {
+            // either the previous layer has no bits set, so there's
+            // trailing data after the end of the tree, or the layer is
+            // truncated.
+            if layer_len == 0 || layer_len > v.len() {
                 return Err(Error::Malformed);
             }
 
@@ -84,14 +262,50 @@ impl Tree {
             v = &v[layer_len..];
         }
 
-        Ok(Self(tree))
+        let tree = Self(tree);
+        tree.validate()?;
+        Ok(tree)
+    }
+
+    /// Check the internal consistency of the [Tree], returning an
+    /// [Error::Malformed] if the structure is broken: the root layer must
+    /// hold exactly one Cell, every other layer must hold exactly one Cell
+    /// per bit set in the layer above it, and the [Tree] must be short
+    /// enough for [Tree::bits] to fit in a `usize`.
+    ///
+    /// Empty Cells (such as those left behind by [Tree::unset]) are
+    /// allowed. Every [Tree] returned by [Tree::from] has been validated.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.0.is_empty() || self.0.len() > Self::max_height() || self.0[0].0.len() != 1 {
+            return Err(Error::Malformed);
+        }
+
+        for layers in self.0.windows(2) {
+            if layers[0].count_ones() != layers[1].0.len() {
+                return Err(Error::Malformed);
+            }
+        }
+        Ok(())
     }
 
     /// Return the largest bit offset representable given the current height
     /// of the [Tree]. If additional capacity is required, the tree can be
     /// grown using [Tree::grow].
     pub fn bits(&self) -> usize {
-        Cell::bits() << (4 * (self.0.len() - 1))
+        Self::layer_shift(self.0.len())
+    }
+
+    /// Return the number of bits the [Tree] can hold, whether they are set
+    /// or not. This is the same as [Tree::bits]; the number of *set* bits is
+    /// [Tree::count_ones].
+    ///
+    /// ```
+    /// let tree = ksq::Tree::from_ones([1, 17]);
+    /// assert_eq!(256, tree.capacity());
+    /// assert_eq!(2, tree.count_ones());
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.bits()
     }
 
     /// Return the height of the tree.
@@ -99,36 +313,322 @@ impl Tree {
         self.0.len()
     }
 
-    /// Grow a [Tree] by one "level". The current implementation will grow by
-    /// `1<<4` each time, due to the current Cell type.
+    /// Return the [Tree::bits] of a [Tree] which is `height` layers tall.
+    fn layer_shift(height: usize) -> usize {
+        1 << (Cell::<T>::bits_log2() * height)
+    }
+
+    /// Return the height of the smallest [Tree] whose [Tree::bits] is larger
+    /// than `max_bit` (with a minimum of `1`). See [Tree::height_for].
+    pub(crate) fn min_height(max_bit: usize) -> usize {
+        if max_bit >= Self::max_bits() {
+            panic!(
+                "capacity out of range {} (max={})",
+                max_bit,
+                Self::max_bits()
+            );
+        }
+        let mut height = 1;
+        while Self::layer_shift(height) <= max_bit {
+            height += 1;
+        }
+        height
+    }
+
+    /// Return the height of the tallest [Tree] whose [Tree::bits] can be
+    /// represented by a `usize` (each layer consumes [Cell::bits_log2] bits
+    /// of offset, `4` for the default `u16` Cell).
+    pub(crate) const fn max_height() -> usize {
+        (usize::BITS as usize - 1) / Cell::<T>::bits_log2()
+    }
+
+    /// Return the [Tree::bits] of a [Tree] which is [Tree::max_height]
+    /// layers tall.
+    pub(crate) fn max_bits() -> usize {
+        Self::layer_shift(Self::max_height())
+    }
+
+    /// Return the number of set bits in the [Tree]. This only needs to
+    /// count the bits of the leaf layer, rather than iterating over every
+    /// bit in the tree.
+    pub fn count_ones(&self) -> usize {
+        self.0[self.0.len() - 1].count_ones()
+    }
+
+    /// Return the number of set bits in each layer of the [Tree], from the
+    /// root down. Each entry (other than the last, which is
+    /// [Tree::count_ones]) is the number of Cells in the layer below it.
+    pub fn count_ones_per_layer(&self) -> Vec<usize> {
+        self.0.iter().map(|layer| layer.count_ones()).collect()
+    }
+
+    /// Return true if no bits are set in the [Tree]. An empty root Cell
+    /// means the [Tree] is empty, otherwise (since [Tree::unset] may leave
+    /// empty Cells behind) this checks the leaf layer, like
+    /// [Tree::count_ones].
+    pub fn is_empty(&self) -> bool {
+        self.0[0].0[0].inner() == T::ZERO || self.count_ones() == 0
+    }
+
+    /// Return the number of set bits strictly before `bit`; so `rank(0)` is
+    /// always `0`, and `rank(bits())` is [Tree::count_ones]. Like
+    /// [Tree::count_ones], this only counts the bits of the leaf layer. If
+    /// the bit is beyond [Tree::bits], a panic will be triggered.
+    pub fn rank(&self, bit: usize) -> usize {
+        if bit > self.bits() {
+            panic!("bit out of range {} (max={})", bit, self.bits());
+        }
+
+        self.leaf_layer()
+            .iter()
+            .take_while(|(offset, _)| *offset < bit)
+            .map(|(offset, cell)| {
+                let o = bit - offset;
+                if o >= Cell::<T>::bits() {
+                    cell.count_ones()
+                } else {
+                    cell.count_ones_until(o)
+                }
+            })
+            .sum()
+    }
+
+    /// Return the number of set bits within `range`, like
+    /// `iter_ones_range(range).count()`, using two [Tree::rank] calls rather
+    /// than iterating over the bits. Any part of the range beyond
+    /// [Tree::bits] is ignored.
+    pub fn count_ones_range(&self, range: Range<usize>) -> usize {
+        let end = range.end.min(self.bits());
+        let start = range.start.min(end);
+        self.rank(end) - self.rank(start)
+    }
+
+    /// Return the offset of the `n`-th (counting from `0`) set bit, or
+    /// `None` if fewer than `n + 1` bits are set. This is the inverse of
+    /// [Tree::rank], so `rank(select(n).unwrap()) == n`.
+    pub fn select(&self, n: usize) -> Option<usize> {
+        let mut remaining = n;
+        for (offset, cell) in self.leaf_layer() {
+            let ones = cell.count_ones();
+            if remaining >= ones {
+                remaining -= ones;
+                continue;
+            }
+            return cell.iter_ones().nth(remaining).map(|idx| offset + idx);
+        }
+        None
+    }
+
+    /// Return the number of bytes of memory used by the [Tree], including
+    /// the [Tree] itself, and the allocated (not just used) capacity of
+    /// each of the layers.
+    pub fn memory_size(&self) -> usize {
+        let layers = self.0.capacity() * size_of::<Layer<T>>();
+        let cells: usize = self
+            .0
+            .iter()
+            .map(|layer| layer.0.capacity() * size_of::<Cell<T>>())
+            .sum();
+        size_of::<Self>() + layers + cells
+    }
+
+    /// Grow a [Tree] by one "level". Each level multiplies [Tree::bits] by
+    /// the number of bits in a Cell (`16` for the default `u16` Cell, `64`
+    /// for a `u64` Cell).
+    ///
+    /// The existing bits keep their offsets: the new root Cell only has its
+    /// first bit set, pointing at the old root. If the [Tree] is empty
+    /// ([Tree::is_empty]), the new root Cell is empty instead, and every
+    /// layer below it holds no Cells, so a set root bit always leads to a
+    /// set bit -- the grown [Tree] is identical to [Tree::with_height] of
+    /// the new height.
+    ///
+    /// If the grown [Tree::bits] would not be representable by a `usize`,
+    /// a panic will be triggered, and the [Tree] is not modified.
     pub fn grow(&mut self) {
-        self.0.insert(0, Layer(vec![1.into()]));
+        self.grow_n(1);
+    }
+
+    /// Grow a [Tree] by `n` "levels", exactly like calling [Tree::grow] `n`
+    /// times, but shifting the existing layers only once.
+    pub fn grow_n(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        match self.height().checked_add(n) {
+            Some(height) if height <= Self::max_height() => {}
+            _ => panic!(
+                "capacity out of range {} (max={})",
+                self.height().saturating_add(n),
+                Self::max_height()
+            ),
+        }
+        if self.is_empty() {
+            *self = Self::from_sorted_ones(self.height() + n, []);
+            return;
+        }
+        self.0
+            .splice(0..0, (0..n).map(|_| Layer(vec![T::ONE.into()])));
+    }
+
+    /// Shrink a [Tree] by one "level", undoing a [Tree::grow]. This is only
+    /// possible when every set bit is within the first Cell-sized fraction
+    /// of the [Tree] (`1/16th`, for the default `u16` Cell -- that is, only
+    /// the first bit of the root Cell is set). If any
+    /// other bit of the root Cell is set, or the [Tree] is only one layer
+    /// tall, an [Error::CannotShrink] is returned, and the [Tree] is not
+    /// modified.
+    pub fn shrink(&mut self) -> Result<(), Error> {
+        if self.0.len() == 1 {
+            return Err(Error::CannotShrink);
+        }
+
+        let root = self.0[0].0[0].inner();
+        if root == T::ZERO {
+            // the tree is empty, so the layers below are too.
+            self.0.remove(0);
+            self.0[0] = Layer(vec![Cell::new()]);
+        } else if root == T::ONE {
+            self.0.remove(0);
+        } else {
+            return Err(Error::CannotShrink);
+        }
+        Ok(())
+    }
+
+    /// [Tree::grow] the [Tree] until it can hold at least `min_bits` bits,
+    /// returning the number of layers added. If the [Tree] is already large
+    /// enough, this will do nothing, and return `0`. If `min_bits` is larger
+    /// than the largest capacity representable by a `usize`, a panic will be
+    /// triggered.
+    pub fn grow_to(&mut self, min_bits: usize) -> usize {
+        let mut added = 0;
+        while self.bits() < min_bits {
+            if self.height() >= Self::max_height() {
+                panic!("capacity out of range {} (max={})", min_bits, self.bits());
+            }
+            self.grow();
+            added += 1;
+        }
+        added
     }
 
     /// Return true/false if the requested bit is set/unset. If the bit
     /// is out of range, a panic will be triggered.
     pub fn get(&self, bit: usize) -> bool {
-        if self.bits() <= bit {
-            panic!("bit out of range {} (max={})", bit, self.bits());
-        }
+        self.try_get(bit)
+            .unwrap_or_else(|_| panic!("bit out of range {} (max={})", bit, self.bits()))
+    }
+
+    /// Return true/false if the requested bit is set/unset. If the bit
+    /// is out of range, an [Error::OutOfRange] will be returned.
+    pub fn try_get(&self, bit: usize) -> Result<bool, Error> {
+        self.check_range(bit)?;
+        Ok(self.lookup(bit).1)
+    }
+
+    /// Return the number of layers a [Tree::get] of the requested bit
+    /// visits before finding an unset bit (which is [Tree::height] if the
+    /// bit is set, or only unset in the leaf layer). A bit within an
+    /// empty part of the [Tree] terminates early, so `1` means that the bit
+    /// is unset in the root Cell. If the bit is out of range, a panic will
+    /// be triggered.
+    pub fn depth_of(&self, bit: usize) -> usize {
+        self.check_range(bit)
+            .unwrap_or_else(|_| panic!("bit out of range {} (max={})", bit, self.bits()));
+        self.lookup(bit).0
+    }
+
+    /// Walk the [Tree] from the root to the requested bit, which must be in
+    /// range, returning the number of layers visited and whether the bit is
+    /// set.
+    fn lookup(&self, bit: usize) -> (usize, bool) {
         let mut next_offset = 0;
         let mut set = false;
         for height in (0..self.0.len()).rev() {
             let layer_index = (self.0.len() - height) - 1;
             (next_offset, set) = self.0[layer_index].get((height, next_offset, bit));
             if !set {
-                return false;
+                return (layer_index + 1, false);
             }
         }
-        set
+        (self.0.len(), set)
+    }
+
+    /// Return true/false if the requested bit is set/unset, like
+    /// [Tree::try_get]. If the bit is out of range, `None` will be returned.
+    pub fn get_checked(&self, bit: usize) -> Option<bool> {
+        self.try_get(bit).ok()
+    }
+
+    /// Return true/false for each of the requested bits, in the same order
+    /// as `bits`. Rather than walking from the root for each bit like
+    /// [Tree::get], the bits are sorted, and answered in a single pass over
+    /// the leaf layer. If any bit is out of range, a panic will be
+    /// triggered.
+    pub fn get_many(&self, bits: &[usize]) -> Vec<bool> {
+        let mut order: Vec<usize> = (0..bits.len()).collect();
+        order.sort_unstable_by_key(|idx| bits[*idx]);
+        let sorted: Vec<usize> = order.iter().map(|idx| bits[*idx]).collect();
+
+        let mut ret = vec![false; bits.len()];
+        for (idx, set) in order.into_iter().zip(self.get_sorted(&sorted)) {
+            ret[idx] = set;
+        }
+        ret
+    }
+
+    /// Return true/false for each of the requested bits, like
+    /// [Tree::get_many], but without sorting them first: `sorted_bits`
+    /// *must* already be sorted ascending, and is answered in a single pass
+    /// over the leaf layer. If it is not sorted, set bits may be reported
+    /// as unset. If any bit is out of range, a panic will be triggered.
+    pub fn get_sorted(&self, sorted_bits: &[usize]) -> Vec<bool> {
+        debug_assert!(sorted_bits.is_sorted());
+
+        let leaf_layer = self.leaf_layer();
+        let mut cells = leaf_layer.iter().peekable();
+        sorted_bits
+            .iter()
+            .map(|&bit| {
+                if bit >= self.bits() {
+                    panic!("bit out of range {} (max={})", bit, self.bits());
+                }
+                while cells
+                    .next_if(|(offset, _)| offset + Cell::<T>::bits() <= bit)
+                    .is_some()
+                {}
+                match cells.peek() {
+                    Some((offset, cell)) if *offset <= bit => cell.get(bit - offset),
+                    _ => false,
+                }
+            })
+            .collect()
+    }
+
+    /// Check that the requested bit is addressable by this [Tree].
+    fn check_range(&self, bit: usize) -> Result<(), Error> {
+        if self.bits() <= bit {
+            return Err(Error::OutOfRange {
+                bit,
+                max: self.bits(),
+            });
+        }
+        Ok(())
     }
 
     /// Set the requested bit to true. If the bit is out of range, a panic
     /// will be triggered.
     pub fn set(&mut self, bit: usize) {
-        if self.bits() <= bit {
-            panic!("bit out of range {} (max={})", bit, self.bits());
-        }
+        self.try_set(bit)
+            .unwrap_or_else(|_| panic!("bit out of range {} (max={})", bit, self.bits()))
+    }
+
+    /// Set the requested bit to true. If the bit is out of range, an
+    /// [Error::OutOfRange] will be returned, and the [Tree] will not be
+    /// modified.
+    pub fn try_set(&mut self, bit: usize) -> Result<(), Error> {
+        self.check_range(bit)?;
         let mut next_offset = 0;
         let mut should_create = false;
         for height in (0..self.0.len()).rev() {
@@ -139,6 +639,98 @@ impl Tree {
             }
             (next_offset, should_create) = self.0[layer_index].set((height, next_offset, bit));
         }
+        Ok(())
+    }
+
+    /// Set each of the provided bits, calling [Tree::grow_to] once if the
+    /// largest bit is beyond the current capacity of the [Tree]. The result
+    /// is the same as calling [Tree::set] for each bit, but rather than
+    /// inserting new Cells into each layer one at a time, the bits are
+    /// sorted, and each layer is rebuilt in a single pass.
+    ///
+    /// If a bit is beyond the largest capacity representable by a `usize`,
+    /// a panic will be triggered.
+    pub fn set_many(&mut self, bits: &[usize]) {
+        let mut bits = bits.to_vec();
+        bits.sort_unstable();
+        bits.dedup();
+
+        let Some(&max) = bits.last() else {
+            return;
+        };
+        let min_bits = max
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("capacity out of range {} (max={})", max, Self::max_bits()));
+        self.grow_to(min_bits);
+
+        // starting offset of each of the existing Cells of the current layer.
+        let mut offsets = vec![0];
+
+        for layer_index in 0..self.0.len() {
+            let height = self.0.len() - layer_index - 1;
+            let cell_bits = Layer::<T>::layer_bits(height);
+            let bits_per_bit = cell_bits >> Cell::<T>::bits_log2();
+
+            let layer = mem::take(&mut self.0[layer_index].0);
+            let mut cells = Vec::with_capacity(layer.len());
+            let mut next_offsets = vec![];
+
+            let mut old = layer.into_iter().zip(offsets).peekable();
+            let mut new = bits.iter().copied().peekable();
+            loop {
+                let next_new = new.peek().map(|bit| bit - (bit % cell_bits));
+                let take_old = match (old.peek(), next_new) {
+                    (None, None) => break,
+                    (Some(&(_, offset)), Some(next_new)) => offset <= next_new,
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                };
+
+                let (offset, mut cell) = match old.next_if(|_| take_old) {
+                    Some((cell, offset)) => {
+                        // the next layer only holds Cells for the bits which
+                        // were already set.
+                        for idx in 0..Cell::<T>::bits() {
+                            if cell.get(idx) {
+                                next_offsets.push(offset + (bits_per_bit * idx));
+                            }
+                        }
+                        (offset, cell)
+                    }
+                    None => (next_new.unwrap(), Cell::new()),
+                };
+
+                while let Some(bit) = new.next_if(|bit| *bit < offset + cell_bits) {
+                    cell = cell.set((bit - offset) / bits_per_bit, true);
+                }
+                cells.push(cell);
+            }
+
+            self.0[layer_index].0 = cells;
+            offsets = next_offsets;
+        }
+    }
+
+    /// Set the bit `offset + j` for every set bit `j` in `other`, calling
+    /// [Tree::grow_to] if needed, like [Tree::set_many]. If a bit would be
+    /// beyond the largest capacity representable by a `usize`, a panic will
+    /// be triggered.
+    pub fn append_at(&mut self, offset: usize, other: &Tree<T>) {
+        let bits: Vec<usize> = other
+            .iter_ones()
+            .map(|bit| {
+                offset.checked_add(bit).unwrap_or_else(|| {
+                    panic!("capacity out of range {} (max={})", bit, Self::max_bits())
+                })
+            })
+            .collect();
+        self.set_many(&bits);
+    }
+
+    /// Lay out the set bits of `other` immediately after the current
+    /// capacity of `self`, like `append_at(self.bits(), other)`.
+    pub fn append(&mut self, other: &Tree<T>) {
+        self.append_at(self.bits(), other);
     }
 
     /// Set the requested bit to false. This will *only* set the lowest level
@@ -146,11 +738,17 @@ impl Tree {
     ///
     /// This means setting all values to true, then unsetting them all will
     /// result in a different tree than initalizing the tree with only the
-    /// required set bits.
+    /// required set bits, until [Tree::prune] is called.
     pub fn unset(&mut self, bit: usize) {
-        if self.bits() <= bit {
-            panic!("bit out of range {} (max={})", bit, self.bits());
-        }
+        self.try_unset(bit)
+            .unwrap_or_else(|_| panic!("bit out of range {} (max={})", bit, self.bits()))
+    }
+
+    /// Set the requested bit to false, like [Tree::unset]. If the bit is
+    /// out of range, an [Error::OutOfRange] will be returned, and the [Tree]
+    /// will not be modified.
+    pub fn try_unset(&mut self, bit: usize) -> Result<(), Error> {
+        self.check_range(bit)?;
         let mut next_offset = 0;
         for height in (0..self.0.len()).rev() {
             let layer_index = (self.0.len() - height) - 1;
@@ -162,15 +760,183 @@ impl Tree {
                 let set;
                 (next_offset, set) = self.0[layer_index].get(li);
                 if !set {
-                    return;
+                    return Ok(());
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Unset every bit outside of the provided range, keeping only the bits
+    /// within it. Any part of the range beyond [Tree::bits] is ignored.
+    ///
+    /// Like [Tree::unset], this will *not* shrink the capacity of the
+    /// [Tree], or remove the now-empty Cells; use [Tree::prune] to remove
+    /// them.
+    pub fn retain_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.bits());
+        let start = range.start.min(end);
+
+        let outside: Vec<usize> = self
+            .iter_ones_range(0..start)
+            .chain(self.iter_ones_range(end..self.bits()))
+            .collect();
+        for bit in outside {
+            self.unset(bit);
+        }
+    }
+
+    /// Keep only the set bits for which `f` returns true, unsetting the rest.
+    /// `f` is only called for the bits which are set, in ascending order.
+    /// Unlike [Tree::retain_range], the [Tree] is then [Tree::prune]-d, so it
+    /// will not contain any empty Cells; the height is not changed.
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        let cells: Vec<(usize, Cell<T>)> = self
+            .leaf_layer()
+            .into_iter()
+            .map(|(offset, mut cell)| {
+                for idx in 0..Cell::<T>::bits() {
+                    if cell.get(idx) && !f(offset + idx) {
+                        cell = cell.set(idx, false);
+                    }
+                }
+                (offset, cell)
+            })
+            .collect();
+        *self = Self::from_leaf_cells(self.height(), cells);
+    }
+
+    /// Split the set bits of the [Tree] at `bit`, returning a [Tree] holding
+    /// every set bit below `bit`, and a [Tree] holding every set bit at or
+    /// after `bit`. The bits are *not* shifted, so both trees are as tall
+    /// as `self`, and each bit keeps its offset; the [Tree::union] of the
+    /// two is the [Tree::prune]-d `self`. This splits the leaf Cells
+    /// directly, rather than iterating over the set bits.
+    pub fn split_at(&self, bit: usize) -> (Tree<T>, Tree<T>) {
+        let (mut below, mut above) = (vec![], vec![]);
+        for (offset, cell) in self.leaf_layer() {
+            if offset + Cell::<T>::bits() <= bit {
+                below.push((offset, cell));
+            } else if offset >= bit {
+                above.push((offset, cell));
+            } else {
+                let mask = (T::ONE << (bit - offset)) - T::ONE;
+                below.push((offset, cell.clear_mask(!mask)));
+                above.push((offset, cell.clear_mask(mask)));
+            }
+        }
+        (
+            Self::from_leaf_cells(self.height(), below),
+            Self::from_leaf_cells(self.height(), above),
+        )
+    }
+
+    /// Return a new [Tree] with every set bit `i` moved to `i + delta`. Bits
+    /// which would move below `0` are dropped. The new [Tree] is at least as
+    /// tall as `self`, and grown to fit any bit moved beyond [Tree::bits]. If
+    /// a bit would move beyond the largest capacity representable by a
+    /// `usize` (see [Tree::grow_to]), a panic will be triggered.
+    pub fn shift(&self, delta: isize) -> Tree<T> {
+        let ones: Vec<usize> = self
+            .iter_ones()
+            .filter_map(|bit| match bit.checked_add_signed(delta) {
+                Some(bit) => Some(bit),
+                None if delta < 0 => None,
+                None => panic!("capacity out of range {} (max={})", bit, Self::max_bits()),
+            })
+            .collect();
+
+        let height = match ones.last() {
+            Some(max) => self.height().max(Self::min_height(*max)),
+            None => self.height(),
+        };
+        Self::from_sorted_ones(height, ones)
+    }
+
+    /// Return the number of unset bits from the top of the [Tree] (bit
+    /// `bits() - 1`) down to the highest set bit. If no bits are set, this
+    /// will return [Tree::bits].
+    pub fn leading_zeros(&self) -> usize {
+        match self.last_one() {
+            Some(highest) => self.bits() - 1 - highest,
+            None => self.bits(),
+        }
+    }
+
+    /// Return the number of unset bits from the bottom of the [Tree] (bit
+    /// `0`) up to the lowest set bit. If no bits are set, this will return
+    /// [Tree::bits].
+    pub fn trailing_zeros(&self) -> usize {
+        self.first_one().unwrap_or(self.bits())
+    }
+
+    /// Return the offset of the lowest set bit, or `None` if no bits are
+    /// set. This only needs to find the first non-empty leaf Cell, rather
+    /// than iterating over every bit in the tree.
+    pub fn first_one(&self) -> Option<usize> {
+        let leaf_layer = self.leaf_layer();
+        let (offset, cell) = leaf_layer
+            .iter()
+            .find(|(_, cell)| cell.inner() != T::ZERO)?;
+        Some(offset + cell.trailing_zeros() as usize)
+    }
+
+    /// Return the offset of the highest set bit, or `None` if no bits are
+    /// set. This only needs to find the last non-empty leaf Cell, rather
+    /// than iterating over every bit in the tree.
+    pub fn last_one(&self) -> Option<usize> {
+        let leaf_layer = self.leaf_layer();
+        let (offset, cell) = leaf_layer
+            .iter()
+            .rev()
+            .find(|(_, cell)| cell.inner() != T::ZERO)?;
+        Some(offset + Cell::<T>::bits() - 1 - cell.leading_zeros() as usize)
+    }
+
+    /// Return the offset of the lowest set bit strictly after `after`, or
+    /// `None` if there is none. Rather than building a leaf layer like
+    /// [Tree::iter_ones_from], this descends the [Tree] from the root like
+    /// [Tree::get], only backtracking to try the next set bit of a Cell when
+    /// a subtree holds nothing at or after the bit being searched for.
+    pub fn next_one(&self, after: usize) -> Option<usize> {
+        let bit = after.checked_add(1)?;
+        if bit >= self.bits() {
+            return None;
+        }
+        self.next_one_in(0, 0, 0, bit)
+    }
+
+    /// Return the lowest set bit at or after `bit` within Cell `cell_index`
+    /// of layer `layer_index`, which starts at bit `offset` of the [Tree].
+    fn next_one_in(
+        &self,
+        layer_index: usize,
+        cell_index: usize,
+        offset: usize,
+        bit: usize,
+    ) -> Option<usize> {
+        let layer = &self.0[layer_index];
+        let height = self.0.len() - layer_index - 1;
+        let bits_per_bit = Layer::<T>::layer_bits(height) >> Cell::<T>::bits_log2();
+        let cell = layer.0[cell_index];
+        let first = bit.saturating_sub(offset) / bits_per_bit;
+
+        for idx in cell.iter_ones().filter(|idx| *idx >= first) {
+            let child_offset = offset + (bits_per_bit * idx);
+            if height == 0 {
+                return Some(child_offset);
+            }
+            let child_index = layer.0[0..cell_index].count_ones() + cell.count_ones_until(idx);
+            if let Some(found) = self.next_one_in(layer_index + 1, child_index, child_offset, bit) {
+                return Some(found);
+            }
+        }
+        None
     }
 
     /// Turn the tree into a [Vec] of Cells -- this can be exported,
     /// and later re-loaded to create the same [Tree] again.
-    pub fn to_vec(&self) -> Vec<CellRepr> {
+    pub fn to_vec(&self) -> Vec<T> {
         let mut ret = vec![];
         for layer in self.0.iter() {
             ret.append(&mut layer.to_vec());
@@ -178,8 +944,167 @@ impl Tree {
         ret
     }
 
+    /// Turn the tree into a [Vec] of Cells, like [Tree::to_vec], but without
+    /// any of the empty Cells left behind by [Tree::unset]. Two trees of
+    /// the same height with the same set bits will always return the same
+    /// Cells, no matter how they were constructed.
+    pub fn compact_to_vec(&self) -> Vec<T> {
+        self.pruned().to_vec()
+    }
+
+    /// Unset every bit in the [Tree], without changing its height. The
+    /// layers keep their allocated capacity, so the [Tree] can be re-used
+    /// without re-growing or re-allocating. Afterwards, the [Tree] is
+    /// identical to [Tree::with_height] of the same height.
+    pub fn clear(&mut self) {
+        for layer in self.0.iter_mut() {
+            layer.0.clear();
+        }
+        self.0[0].0.push(Cell::new());
+    }
+
+    /// Remove every empty Cell left behind by [Tree::unset] (clearing the
+    /// bit pointing to it in the layer above, and so on up the tree). The
+    /// height of the [Tree] is not changed.
+    ///
+    /// After pruning, the [Tree] is identical to one built by only setting
+    /// the bits which remain set.
+    pub fn prune(&mut self) {
+        *self = self.pruned();
+    }
+
+    /// Return a copy of this [Tree] without any empty Cells.
+    fn pruned(&self) -> Self {
+        Self::from_leaf_cells(self.height(), self.leaf_layer())
+    }
+
+    /// Build a [Tree] of the provided height with only the provided bits
+    /// set, laying out each layer in order. The bits *must* be sorted
+    /// ascending, and must be in range for the height. The resulting tree
+    /// will not contain any empty Cells (other than the root).
+    pub(crate) fn from_sorted_ones(height: usize, ones: impl IntoIterator<Item = usize>) -> Self {
+        Self::from_leaf_cells(
+            height,
+            ones.into_iter().map(|bit| {
+                let o = bit % Cell::<T>::bits();
+                (bit - o, Cell::new().set(o, true))
+            }),
+        )
+    }
+
+    /// Build a [Tree] of the provided height from a set of leaf Cells and
+    /// their starting offset in the tree (as returned by [Tree::leaf_layer]),
+    /// laying out each layer in order. The offsets *must* be sorted
+    /// ascending, and must be in range for the height. Cells sharing an
+    /// offset are merged, and empty Cells are dropped, so the resulting tree
+    /// will not contain any empty Cells (other than the root).
+    pub(crate) fn from_leaf_cells(
+        height: usize,
+        cells: impl IntoIterator<Item = (usize, Cell<T>)>,
+    ) -> Self {
+        let mut tree = Tree(vec![Layer(vec![Cell::new()])]);
+        tree.0.extend((1..height).map(|_| Layer(vec![])));
+
+        // global index of the last cell pushed onto each layer.
+        let mut last_cell = vec![None; height];
+        last_cell[0] = Some(0);
+
+        for (offset, leaf) in cells {
+            debug_assert!(offset < tree.bits());
+            if leaf.inner() == T::ZERO {
+                continue;
+            }
+
+            for (layer_index, layer) in tree.0.iter_mut().enumerate() {
+                let height = height - layer_index - 1;
+                let cell_index = offset / Layer::<T>::layer_bits(height);
+
+                if last_cell[layer_index] != Some(cell_index) {
+                    debug_assert!(last_cell[layer_index] < Some(cell_index));
+                    last_cell[layer_index] = Some(cell_index);
+                    layer.0.push(Default::default());
+                }
+
+                let o = layer.cell_bit((height, 0, offset));
+                let cell = layer.0.last_mut().unwrap();
+                *cell = if height == 0 {
+                    cell.with_mask(leaf.inner())
+                } else {
+                    cell.set(o, true)
+                };
+            }
+        }
+
+        tree
+    }
+
+    /// Return a read-only view of each layer of the [Tree], from the root
+    /// down, as a slice of its Cells (see [Cell::inner]). There is always
+    /// one layer per [Tree::height], and each layer holds one Cell per bit
+    /// set in the layer above it. This is exactly the layout of
+    /// [Tree::to_vec], split by layer.
+    pub fn layers(&self) -> impl ExactSizeIterator<Item = &[Cell<T>]> + DoubleEndedIterator {
+        self.0.iter().map(|layer| layer.0.as_slice())
+    }
+
+    /// Iterate over every Cell of the [Tree], along with the index of its
+    /// layer (from the root down) and its index within that layer, in
+    /// exactly the order of [Tree::to_vec].
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, Cell<T>)> {
+        self.layers().enumerate().flat_map(|(layer_index, layer)| {
+            layer
+                .iter()
+                .enumerate()
+                .map(move |(cell_index, cell)| (layer_index, cell_index, *cell))
+        })
+    }
+
+    /// Return a human-readable dump of every layer of the [Tree], from the
+    /// root down. Each layer is listed with its Cell count and popcount,
+    /// followed by each of its Cells (highest bit first) and the first bit
+    /// offset that Cell covers.
+    pub fn debug_layers(&self) -> String {
+        let mut ret = String::new();
+        let mut offsets = vec![0];
+
+        for (layer_index, layer) in self.0.iter().enumerate() {
+            let height = self.0.len() - layer_index - 1;
+            let bits_per_bit = Layer::<T>::layer_bits(height) / Cell::<T>::bits();
+
+            writeln!(
+                ret,
+                "layer {}: {} cells, {} ones",
+                layer_index,
+                layer.0.len(),
+                layer.count_ones(),
+            )
+            .unwrap();
+
+            let mut next_offsets = vec![];
+            for (cell, offset) in layer.0.iter().zip(offsets.iter()) {
+                writeln!(
+                    ret,
+                    "  @{}: {:0width$b}",
+                    offset,
+                    cell.inner(),
+                    width = Cell::<T>::bits()
+                )
+                .unwrap();
+
+                for idx in 0..Cell::<T>::bits() {
+                    if cell.get(idx) {
+                        next_offsets.push(offset + (bits_per_bit * idx));
+                    }
+                }
+            }
+            offsets = next_offsets;
+        }
+
+        ret
+    }
+
     /// Return a mapping of Cells and their starting offset in the tree.
-    pub(crate) fn leaf_layer(&self) -> Vec<(usize, Cell)> {
+    pub(crate) fn leaf_layer(&self) -> Vec<(usize, Cell<T>)> {
         // for each layer from the top down, let's compute the starting indexes
         // for each one.
 
@@ -192,12 +1117,12 @@ impl Tree {
             // number of bits that a 1 represents. At the highest level, this
             // is the number of bits representable in the tree. At the lowest
             // level this is '1' bit per bit.
-            let bits_per_bit = 1 << (4 * height);
+            let bits_per_bit = 1 << (Cell::<T>::bits_log2() * height);
 
             let mut next_layer_map = vec![];
 
             for (cell, offset) in self.0[layer_index].0.iter().zip(layer_map.iter()) {
-                for idx in 0..16usize {
+                for idx in 0..Cell::<T>::bits() {
                     if cell.get(idx) {
                         // if set, let's add it to the map
                         next_layer_map.push(offset + (bits_per_bit * idx));
@@ -240,23 +1165,564 @@ mod tests {
     }
 
     #[test]
-    fn tree_parse_00_error() {
-        assert!(Tree::from(&[0, 0]).is_err());
+    fn tree_with_height() {
+        assert_eq!(Tree::new(), Tree::with_height(0));
+        assert_eq!(Tree::new(), Tree::with_height(1));
+
+        let mut tree = Tree::with_height(3);
+        assert_eq!(3, tree.height());
+        assert_eq!(4096, tree.bits());
+        assert_eq!(None, tree.iter_ones().next());
+        assert_eq!(vec![0], tree.to_vec());
+
+        tree.set(4095);
+        assert!(tree.get(4095));
+        assert_eq!(vec![4095], tree.iter_ones().collect::<Vec<_>>());
     }
 
     #[test]
-    fn tree_mega() {
-        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
-        for idx in 0..4096 {
-            assert!(!tree.get(idx));
-            tree.set(idx);
-            assert!(tree.get(idx));
-        }
+    fn tree_layers() {
+        let mut tree = Tree::from_ones([17, 19, 4095]);
+        tree.unset(19);
+
+        assert_eq!(tree.height(), tree.layers().len());
+        assert_eq!(
+            vec![vec![0x8001], vec![0x0002, 0x8000], vec![0x0002, 0x8000]],
+            tree.layers()
+                .map(|layer| layer.iter().map(Cell::inner).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.to_vec(),
+            tree.layers().flatten().map(Cell::inner).collect::<Vec<_>>()
+        );
+
+        let tree = Tree::with_height(4);
+        assert_eq!(4, tree.layers().len());
+        assert_eq!(
+            vec![1, 0, 0, 0],
+            tree.layers().map(<[_]>::len).collect::<Vec<_>>()
+        );
     }
 
     #[test]
-    fn tree_mega_loopback() {
-        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+    fn tree_cells() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+
+        assert_eq!(
+            vec![
+                (0, 0, 0x8001),
+                (1, 0, 0x0002),
+                (1, 1, 0x8000),
+                (2, 0, 0x000A),
+                (2, 1, 0x8000),
+            ],
+            tree.cells()
+                .map(|(layer, index, cell)| (layer, index, cell.inner()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.to_vec(),
+            tree.cells()
+                .map(|(_, _, cell)| cell.inner())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn tree_with_capacity_bits() {
+        let mut tree = Tree::with_capacity_bits(4096);
+        assert_eq!(0, tree.count_ones());
+        assert_eq!(4096, tree.bits());
+        assert_eq!(Tree::with_height(3), tree);
+
+        tree.set(4000);
+        assert_eq!(Tree::from_ones([4000]), tree);
+        assert!(!tree.get(0));
+
+        assert_eq!(4096 * 16, Tree::with_capacity_bits(4097).bits());
+        assert_eq!(Tree::new(), Tree::with_capacity_bits(0));
+        assert_eq!(Tree::new(), Tree::with_capacity_bits(16));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_with_capacity_bits_overflow() {
+        Tree::with_capacity_bits(usize::MAX);
+    }
+
+    #[test]
+    fn tree_debug_layers() {
+        let tree = Tree::from(&[1, 1, 0]).unwrap();
+        assert_eq!(
+            "\
+layer 0: 1 cells, 1 ones
+  @0: 0000000000000001
+layer 1: 1 cells, 1 ones
+  @0: 0000000000000001
+layer 2: 1 cells, 0 ones
+  @0: 0000000000000000
+",
+            tree.debug_layers()
+        );
+
+        let tree = Tree::from(&[0x8002, 0x0400, 0x0001, 0x0001, 0x0003]).unwrap();
+        assert_eq!(
+            "\
+layer 0: 1 cells, 2 ones
+  @0: 1000000000000010
+layer 1: 2 cells, 2 ones
+  @256: 0000010000000000
+  @3840: 0000000000000001
+layer 2: 2 cells, 3 ones
+  @416: 0000000000000001
+  @3840: 0000000000000011
+",
+            tree.debug_layers()
+        );
+    }
+
+    #[test]
+    fn tree_try_get() {
+        let tree = Tree::from(&[1, 1, 1]).unwrap();
+        assert_eq!(Ok(true), tree.try_get(0));
+        assert_eq!(Ok(false), tree.try_get(4095));
+        assert_eq!(
+            Err(Error::OutOfRange {
+                bit: 4096,
+                max: 4096
+            }),
+            tree.try_get(4096)
+        );
+        assert_eq!(
+            Err(Error::OutOfRange {
+                bit: usize::MAX,
+                max: 4096
+            }),
+            tree.try_get(usize::MAX)
+        );
+    }
+
+    #[test]
+    fn tree_depth_of() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+        assert_eq!(3, tree.height());
+
+        assert_eq!(3, tree.depth_of(17));
+        assert_eq!(3, tree.depth_of(4095));
+        assert_eq!(3, tree.depth_of(18));
+        assert_eq!(2, tree.depth_of(40));
+        assert_eq!(1, tree.depth_of(300));
+        assert_eq!(1, tree.depth_of(4000 - 256));
+
+        assert_eq!(1, Tree::with_height(4).depth_of(0));
+        assert_eq!(1, Tree::new().depth_of(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_depth_of_out_of_range() {
+        Tree::new().depth_of(16);
+    }
+
+    #[test]
+    fn tree_get_checked() {
+        let mut tree = Tree::new();
+        assert_eq!(Some(false), tree.get_checked(0));
+        assert_eq!(None, tree.get_checked(tree.bits()));
+
+        tree.set(15);
+        assert_eq!(Some(true), tree.get_checked(15));
+        assert_eq!(None, tree.get_checked(16));
+    }
+
+    #[test]
+    fn tree_try_set_unset() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        assert_eq!(Ok(()), tree.try_set(17));
+        assert_eq!(Ok(()), tree.try_set(4095));
+        assert_eq!(Ok(()), tree.try_unset(4095));
+        assert_eq!(vec![17], tree.iter_ones().collect::<Vec<_>>());
+
+        let before = tree.clone();
+        let err = Err(Error::OutOfRange {
+            bit: 4096,
+            max: 4096,
+        });
+        assert_eq!(err, tree.try_set(4096));
+        assert_eq!(before, tree);
+        assert_eq!(err, tree.try_unset(4096));
+        assert_eq!(before, tree);
+        assert!(tree.try_set(usize::MAX).is_err());
+        assert_eq!(before, tree);
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_set_out_of_range() {
+        Tree::new().set(16);
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_get_out_of_range() {
+        Tree::new().get(16);
+    }
+
+    #[test]
+    fn tree_from_ones() {
+        let tree = Tree::from_ones([4095, 17, 19, 17]);
+
+        let mut manual = Tree::new();
+        manual.grow();
+        manual.grow();
+        for idx in [17, 19, 4095] {
+            manual.set(idx);
+        }
+        manual.prune();
+
+        assert_eq!(manual, tree);
+        assert_eq!(4096, tree.bits());
+        assert_eq!(vec![17, 19, 4095], tree.iter_ones().collect::<Vec<_>>());
+
+        assert_eq!(Tree::new(), Tree::from_ones([]));
+        assert_eq!(1, Tree::from_ones([15]).height());
+        assert_eq!(2, Tree::from_ones([16]).height());
+        assert_eq!(3, Tree::from_ones([256]).height());
+    }
+
+    #[test]
+    fn tree_from_bits() {
+        let tree = Tree::from_ones([1, 17, 19, 4000]);
+        let bits: Vec<bool> = tree.iter().collect();
+        assert_eq!(tree, Tree::from_bits(&bits));
+
+        let mut bits = vec![false; 17];
+        bits[16] = true;
+        let tree = Tree::from_bits(&bits);
+        assert_eq!(2, tree.height());
+        assert_eq!(vec![16], tree.iter_ones().collect::<Vec<_>>());
+
+        assert_eq!(Tree::new(), Tree::from_bits(&[]));
+        assert_eq!(Tree::new(), Tree::from_bits(&[false; 16]));
+        assert_eq!(Tree::with_height(2), Tree::from_bits(&[false; 17]));
+    }
+
+    #[test]
+    fn tree_from_iter() {
+        let tree: Tree = vec![4095, 17, 19].into_iter().collect();
+        assert_eq!(Tree::from_ones([17, 19, 4095]), tree);
+
+        let tree: Tree = (0..1000).filter(|idx| idx % 7 == 3).collect();
+        assert_eq!(3, tree.height());
+        assert_eq!(
+            (3..1000).step_by(7).collect::<Vec<_>>(),
+            tree.iter_ones().collect::<Vec<_>>()
+        );
+
+        let tree: Tree = core::iter::empty().collect();
+        assert_eq!(Tree::new(), tree);
+    }
+
+    #[test]
+    fn tree_extend() {
+        let mut tree = Tree::new();
+        tree.extend([3, 9]);
+        assert_eq!(1, tree.height());
+
+        tree.extend([20]);
+        assert_eq!(2, tree.height());
+
+        tree.extend(vec![5000, 100]);
+        assert_eq!(4, tree.height());
+        assert_eq!(
+            vec![3, 9, 20, 100, 5000],
+            tree.iter_ones().collect::<Vec<_>>()
+        );
+        for idx in [3, 9, 20, 100, 5000] {
+            assert!(tree.get(idx));
+        }
+    }
+
+    #[test]
+    fn tree_grow_to() {
+        let mut tree = Tree::new();
+        assert_eq!(0, tree.grow_to(0));
+        assert_eq!(0, tree.grow_to(16));
+        assert_eq!(1, tree.height());
+
+        assert_eq!(1, tree.grow_to(17));
+        assert_eq!(256, tree.bits());
+
+        assert_eq!(2, tree.grow_to(65536));
+        assert_eq!(65536, tree.bits());
+        assert_eq!(0, tree.grow_to(100));
+
+        let mut tree = Tree::new();
+        tree.grow_to(1 << 60);
+        assert_eq!(1 << 60, tree.bits());
+    }
+
+    #[test]
+    fn tree_grow_max() {
+        let mut tree = Tree::new();
+        tree.grow_n(<Tree>::max_height() - 1);
+        assert_eq!(1 << 60, tree.bits());
+
+        let mut tree = Tree::from_ones([17]);
+        tree.grow_n(<Tree>::max_height() - 2);
+        assert_eq!(1 << 60, tree.bits());
+        assert!(tree.get(17));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_grow_overflow() {
+        let mut tree = Tree::with_height(<Tree>::max_height());
+        tree.grow();
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_with_height_overflow() {
+        Tree::with_height(<Tree>::max_height() + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_grow_n_overflow() {
+        Tree::from_ones([17]).grow_n(usize::MAX);
+    }
+
+    #[test]
+    fn tree_grow_n() {
+        let mut tree = Tree::from_ones([1, 17]);
+        let mut grown = tree.clone();
+
+        grown.grow_n(3);
+        for _ in 0..3 {
+            tree.grow();
+        }
+        assert_eq!(tree, grown);
+        assert_eq!(5, grown.height());
+        assert_eq!(vec![1, 17], grown.iter_ones().collect::<Vec<_>>());
+
+        grown.grow_n(0);
+        assert_eq!(tree, grown);
+    }
+
+    #[test]
+    fn tree_grow_empty() {
+        let mut tree = Tree::new();
+        tree.grow();
+        assert_eq!(vec![0], tree.to_vec());
+        assert_eq!(Tree::with_height(2), tree);
+
+        tree.grow_n(2);
+        assert_eq!(Tree::with_height(4), tree);
+        assert_eq!(0, tree.count_ones());
+
+        // an empty tree with empty Cells left behind by unset.
+        let mut tree = Tree::from_ones([17]);
+        tree.unset(17);
+        assert_eq!(vec![2, 0], tree.to_vec());
+        tree.grow();
+        assert_eq!(Tree::with_height(3), tree);
+
+        let mut tree = Tree::from_ones([17]);
+        tree.grow();
+        assert_eq!(vec![1, 2, 2], tree.to_vec());
+        assert_eq!(vec![17], tree.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tree_height_for() {
+        let mut tree = Tree::new();
+        for height in 1..=<Tree>::max_height() {
+            assert_eq!(tree.height(), height);
+            assert_eq!(tree.bits(), Tree::bits_for(height));
+            assert_eq!(height, Tree::height_for(tree.bits() - 1));
+            if height < <Tree>::max_height() {
+                assert_eq!(height + 1, Tree::height_for(tree.bits()));
+                tree.grow();
+            }
+        }
+        assert_eq!(1, Tree::height_for(0));
+        assert_eq!(3, Tree::height_for(4000));
+        assert_eq!(Tree::from_ones([4000]).height(), Tree::height_for(4000));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_height_for_overflow() {
+        Tree::height_for(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_bits_for_overflow() {
+        Tree::bits_for(<Tree>::max_height() + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn tree_grow_to_overflow() {
+        Tree::new().grow_to(usize::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_extend_overflow() {
+        Tree::new().extend([usize::MAX]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_from_ones_overflow() {
+        Tree::from_ones([3, usize::MAX]);
+    }
+
+    #[test]
+    fn tree_from_ones_max() {
+        let tree = Tree::from_ones([3, <Tree>::max_bits() - 1]);
+        assert_eq!(<Tree>::max_height(), tree.height());
+        assert_eq!(2, tree.count_ones());
+    }
+
+    fn check_cell_int<T: CellInt>(height: usize) {
+        let ones = [0, 3, 200, 5000, 70000];
+        let mut tree: Tree<T> = ones.into_iter().collect();
+        assert_eq!(height, tree.height());
+        assert_eq!(ones.len(), tree.count_ones());
+        assert_eq!(ones.to_vec(), tree.iter_ones().collect::<Vec<_>>());
+        assert!(tree.get(70000) && !tree.get(69999));
+        assert_eq!(tree, Tree::from_cells(&tree.to_vec()).unwrap());
+
+        let other: Tree<T> = [3, 4, 70000].into_iter().collect();
+        assert_eq!(
+            vec![3, 70000],
+            tree.intersection(&other).iter_ones().collect::<Vec<_>>()
+        );
+
+        let bits = tree.bits();
+        tree.grow();
+        assert_eq!(bits * Cell::<T>::bits(), tree.bits());
+        tree.unset(70000);
+        tree.prune();
+        assert_eq!(vec![0, 3, 200, 5000], tree.iter_ones().collect::<Vec<_>>());
+
+        let mut tree = Tree::<T>::default();
+        tree.extend([5000]);
+        let collected: Tree<T> = [5000].into_iter().collect();
+        assert_eq!(collected.height(), tree.height());
+        assert_eq!(vec![5000], tree.iter_ones().collect::<Vec<_>>());
+        tree.grow_to(<Tree<T>>::max_bits());
+        assert_eq!(<Tree<T>>::max_height(), tree.height());
+        assert!(tree.bits().checked_mul(Cell::<T>::bits()).is_none());
+    }
+
+    #[test]
+    fn tree_cell_int() {
+        check_cell_int::<u8>(6);
+        check_cell_int::<u16>(5);
+        check_cell_int::<u32>(4);
+        check_cell_int::<u64>(3);
+
+        let tree: Tree<u64> = [63].into_iter().collect();
+        assert_eq!((1, 64), (tree.height(), tree.bits()));
+        assert_eq!(vec![1 << 63], tree.to_vec());
+    }
+
+    #[test]
+    fn tree_shrink() {
+        let original = Tree::from_ones([3, 17, 200]);
+        let mut tree = original.clone();
+
+        tree.grow();
+        tree.grow();
+        assert_eq!(Ok(()), tree.shrink());
+        assert_eq!(Ok(()), tree.shrink());
+        assert_eq!(original, tree);
+
+        // bits 17 and 200 are outside of the first child of the root.
+        assert_eq!(Err(Error::CannotShrink), tree.shrink());
+        assert_eq!(original, tree);
+
+        let mut tree = Tree::from_ones([3]);
+        assert_eq!(Err(Error::CannotShrink), tree.shrink());
+
+        let mut tree = Tree::with_height(3);
+        assert_eq!(Ok(()), tree.shrink());
+        assert_eq!(Tree::with_height(2), tree);
+        assert_eq!(Ok(()), tree.shrink());
+        assert_eq!(Tree::new(), tree);
+    }
+
+    #[test]
+    fn tree_parse_00_error() {
+        assert!(Tree::from(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn tree_validate() {
+        let mut tree = Tree::from_ones([1, 17, 4000]);
+        tree.unset(17);
+        assert_eq!(Ok(()), tree.validate());
+        assert_eq!(Ok(()), Tree::with_height(5).validate());
+
+        // flip a bit of the middle layer, so the leaf layer is too short.
+        let mut cells = tree.to_vec();
+        cells[1] |= 1 << 4;
+        assert_eq!(Err(Error::Malformed), Tree::from(&cells));
+
+        // drop the last Cell, so the leaf layer is truncated.
+        let mut cells = tree.to_vec();
+        cells.pop();
+        assert_eq!(Err(Error::Malformed), Tree::from(&cells));
+
+        let mut broken = tree.clone();
+        broken.0[2].0.push(Cell::new());
+        assert_eq!(Err(Error::Malformed), broken.validate());
+
+        let mut broken = tree.clone();
+        broken.0[0].0.push(Cell::new());
+        assert_eq!(Err(Error::Malformed), broken.validate());
+
+        let mut cells = vec![1; <Tree>::max_height()];
+        cells.push(0);
+        assert_eq!(Err(Error::Malformed), Tree::from(&cells));
+        cells.truncate(<Tree>::max_height());
+        cells[<Tree>::max_height() - 1] = 0;
+        assert!(Tree::from(&cells).is_ok());
+    }
+
+    #[test]
+    fn tree_parse_trailing_error() {
+        assert!(Tree::from(&[3, 10, 10]).is_ok());
+        assert_eq!(Err(Error::Malformed), Tree::from(&[3, 10, 10, 0]));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from(&[3, 10, 10, 0, 0, 0, 0, 0])
+        );
+
+        assert!(Tree::from(&[1, 0]).is_ok());
+        assert_eq!(Err(Error::Malformed), Tree::from(&[1, 0, 0]));
+        assert_eq!(Err(Error::Malformed), Tree::from(&[0, 0]));
+    }
+
+    #[test]
+    fn tree_mega() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in 0..4096 {
+            assert!(!tree.get(idx));
+            tree.set(idx);
+            assert!(tree.get(idx));
+        }
+    }
+
+    #[test]
+    fn tree_mega_loopback() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
         for idx in 0..4096 {
             assert!(!tree.get(idx));
             tree.set(idx);
@@ -277,6 +1743,610 @@ mod tests {
         }
     }
 
+    /// small xorshift, so the tests are reproducible without a rng crate.
+    fn xorshift(mut state: usize) -> impl FnMut() -> usize {
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        }
+    }
+
+    #[test]
+    fn tree_rank() {
+        let tree = Tree::from_ones([0, 3, 17, 4095]);
+        assert_eq!(0, tree.rank(0));
+        assert_eq!(1, tree.rank(1));
+        assert_eq!(2, tree.rank(17));
+        assert_eq!(3, tree.rank(18));
+        assert_eq!(3, tree.rank(4095));
+        assert_eq!(4, tree.rank(tree.bits()));
+        assert_eq!(0, Tree::with_height(3).rank(4096));
+
+        let mut next = xorshift(0x9E3779B9);
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for _ in 0..300 {
+            tree.set(next() % tree.bits());
+        }
+        for _ in 0..50 {
+            tree.unset(next() % tree.bits());
+        }
+        assert_eq!(tree.count_ones(), tree.rank(tree.bits()));
+        for idx in 0..tree.bits() {
+            assert_eq!(tree.get(idx) as usize, tree.rank(idx + 1) - tree.rank(idx));
+        }
+    }
+
+    #[test]
+    fn tree_count_ones_range() {
+        let tree = Tree::from_ones([0, 1, 15, 16, 17, 31, 32, 200, 4095]);
+
+        for range in [
+            0..0,
+            0..1,
+            1..16,
+            1..17,
+            15..17,
+            16..32,
+            17..31,
+            17..33,
+            3..201,
+            200..4095,
+            0..4096,
+            195..5000,
+            5000..6000,
+        ] {
+            assert_eq!(
+                tree.iter_ones_range(range.clone()).count(),
+                tree.count_ones_range(range.clone()),
+                "{:?}",
+                range
+            );
+        }
+        assert_eq!(tree.count_ones(), tree.count_ones_range(0..usize::MAX));
+    }
+
+    #[test]
+    fn tree_select() {
+        assert_eq!(None, Tree::new().select(0));
+        assert_eq!(None, Tree::with_height(3).select(0));
+
+        let mut next = xorshift(0x2545F491);
+        for sets in [1, 10, 100, 1000] {
+            let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+            for _ in 0..sets {
+                tree.set(next() % tree.bits());
+            }
+            for _ in 0..(sets / 4) {
+                tree.unset(next() % tree.bits());
+            }
+
+            let ones = tree.iter_ones().collect::<Vec<_>>();
+            for (n, one) in ones.iter().enumerate() {
+                assert_eq!(Some(*one), tree.select(n));
+                assert_eq!(n, tree.rank(*one));
+            }
+            assert_eq!(None, tree.select(ones.len()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_rank_out_of_range() {
+        Tree::new().rank(17);
+    }
+
+    #[test]
+    fn tree_is_empty() {
+        assert!(Tree::new().is_empty());
+        assert!(Tree::with_height(4).is_empty());
+
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        assert!(tree.is_empty());
+        tree.set(17);
+        assert!(!tree.is_empty());
+        tree.unset(17);
+        assert!(tree.is_empty());
+        tree.prune();
+        assert!(tree.is_empty());
+
+        assert!(!Tree::from_ones([0]).is_empty());
+    }
+
+    #[test]
+    fn tree_capacity() {
+        let mut tree = Tree::new();
+        assert_eq!((16, 0), (tree.capacity(), tree.count_ones()));
+
+        tree.set(3);
+        tree.grow();
+        assert_eq!(tree.bits(), tree.capacity());
+        assert_eq!((256, 1), (tree.capacity(), tree.count_ones()));
+    }
+
+    #[test]
+    fn tree_count_ones() {
+        assert_eq!(0, Tree::new().count_ones());
+        assert_eq!(0, Tree::with_height(3).count_ones());
+        assert_eq!(0, Tree::from(&[1, 1, 0]).unwrap().count_ones());
+
+        let mut next = xorshift(0x2545F491);
+        for sets in [1, 10, 100, 1000] {
+            let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+            for _ in 0..sets {
+                tree.set(next() % tree.bits());
+            }
+            for _ in 0..(sets / 4) {
+                tree.unset(next() % tree.bits());
+            }
+            assert_eq!(tree.iter_ones().count(), tree.count_ones());
+        }
+    }
+
+    #[test]
+    fn tree_count_ones_per_layer() {
+        let mut tree = Tree::from_ones([1, 3, 17, 19, 4095]);
+        tree.unset(3);
+
+        let ones = tree.count_ones_per_layer();
+        assert_eq!(tree.height(), ones.len());
+        assert_eq!(vec![2, 3, 4], ones);
+        for (ones, layer) in ones.iter().zip(tree.layers().skip(1)) {
+            assert_eq!(*ones, layer.len());
+        }
+        assert_eq!(tree.count_ones(), *ones.last().unwrap());
+
+        assert_eq!(vec![0, 0, 0], Tree::with_height(3).count_ones_per_layer());
+    }
+
+    #[test]
+    fn tree_memory_size() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        let mut size = tree.memory_size();
+        assert!(size >= size_of::<Tree>() + 3 * size_of::<Layer>() + 3 * size_of::<Cell>());
+
+        // each of these lands in a previously empty subtree.
+        for idx in [300, 600, 1200, 2400, 4000] {
+            tree.set(idx);
+            let next = tree.memory_size();
+            assert!(next >= size);
+            size = next;
+        }
+        assert!(size >= size_of::<Tree>() + 3 * size_of::<Layer>() + 13 * size_of::<Cell>());
+    }
+
+    #[test]
+    fn tree_compact_to_vec() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        let mut fresh = tree.clone();
+
+        for idx in [3, 100, 700, 2000, 2001, 4000] {
+            tree.set(idx);
+        }
+        for idx in [100, 2000, 2001] {
+            tree.unset(idx);
+        }
+        for idx in [3, 700, 4000] {
+            fresh.set(idx);
+        }
+
+        assert_ne!(fresh.to_vec(), tree.to_vec());
+        assert_eq!(fresh.to_vec(), tree.compact_to_vec());
+        assert_eq!(fresh.to_vec(), fresh.compact_to_vec());
+
+        let tree = Tree::from(&tree.compact_to_vec()).unwrap();
+        assert_eq!(vec![3, 700, 4000], tree.iter_ones().collect::<Vec<_>>());
+
+        for idx in [3, 700, 4000] {
+            fresh.unset(idx);
+        }
+        assert_eq!(vec![0], fresh.compact_to_vec());
+    }
+
+    /// [Hasher] recording every byte written to it, so hashes can be
+    /// compared without `std`.
+    #[derive(Default)]
+    struct RecordingHasher(Vec<u8>);
+
+    impl Hasher for RecordingHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+    }
+
+    fn hash_of<T: CellInt>(tree: &Tree<T>) -> Vec<u8> {
+        let mut hasher = RecordingHasher::default();
+        tree.hash(&mut hasher);
+        hasher.0
+    }
+
+    #[test]
+    fn tree_hash() {
+        let fresh = Tree::from_ones([1, 17, 4000]);
+
+        let mut toggled = Tree::from_ones([1, 17, 300, 4000]);
+        toggled.unset(300);
+        assert_ne!(fresh, toggled);
+        assert_eq!(hash_of(&fresh), hash_of(&toggled));
+
+        let mut taller = fresh.clone();
+        taller.grow();
+        assert_eq!(hash_of(&fresh), hash_of(&taller));
+
+        assert_ne!(hash_of(&fresh), hash_of(&Tree::from_ones([1, 17])));
+        assert_eq!(hash_of(&Tree::new()), hash_of(&Tree::with_height(3)));
+    }
+
+    #[test]
+    fn tree_clone_from() {
+        let snapshot = Tree::from_ones([1, 17, 4000]);
+
+        let mut scratch = snapshot.clone();
+        scratch.set_many(&[2, 18, 300, 4001]);
+        let leaf = scratch.0[2].0.as_ptr();
+
+        scratch.clone_from(&snapshot);
+        assert_eq!(snapshot, scratch);
+        assert_eq!(leaf, scratch.0[2].0.as_ptr());
+
+        let mut scratch = Tree::new();
+        scratch.clone_from(&snapshot);
+        assert_eq!(snapshot, scratch);
+
+        let mut scratch = Tree::from_ones([1 << 20]);
+        scratch.clone_from(&snapshot);
+        assert_eq!(snapshot, scratch);
+    }
+
+    #[test]
+    fn tree_clear() {
+        let mut tree = Tree::from_ones([3, 17, 300, 4095]);
+        let capacity = tree.memory_size();
+        tree.clear();
+
+        assert_eq!(3, tree.height());
+        assert_eq!(4096, tree.bits());
+        assert_eq!(0, tree.count_ones());
+        assert_eq!(Tree::with_height(3).to_vec(), tree.to_vec());
+        assert_eq!(capacity, tree.memory_size());
+        assert!((0..tree.bits()).all(|idx| !tree.get(idx)));
+
+        tree.set(17);
+        assert_eq!(vec![17], tree.iter_ones().collect::<Vec<_>>());
+
+        let mut tree = Tree::new();
+        tree.set(3);
+        tree.clear();
+        assert_eq!(Tree::new(), tree);
+    }
+
+    #[test]
+    fn tree_get_many() {
+        let mut tree = Tree::from_ones([0, 17, 300, 4095]);
+        tree.unset(300);
+
+        let bits = [4095, 0, 17, 18, 300, 17, 2000];
+        assert_eq!(
+            bits.iter().map(|bit| tree.get(*bit)).collect::<Vec<_>>(),
+            tree.get_many(&bits)
+        );
+        assert_eq!(
+            vec![true, true, true, false, false, true, false],
+            tree.get_many(&bits)
+        );
+        assert_eq!(Vec::<bool>::new(), tree.get_many(&[]));
+    }
+
+    #[test]
+    fn tree_get_sorted() {
+        let mut tree = Tree::from_ones([1, 17, 19, 200, 4000]);
+        tree.unset(19);
+
+        let bits: Vec<usize> = (0..tree.bits()).step_by(3).collect();
+        assert_eq!(
+            bits.iter().map(|bit| tree.get(*bit)).collect::<Vec<_>>(),
+            tree.get_sorted(&bits)
+        );
+        assert_eq!(
+            vec![true, true, true, false, true, true],
+            tree.get_sorted(&[1, 1, 17, 19, 200, 4000])
+        );
+        assert!(tree.get_sorted(&[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_get_sorted_out_of_range() {
+        Tree::new().get_sorted(&[1, 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bit out of range")]
+    fn tree_get_many_out_of_range() {
+        Tree::new().get_many(&[3, 16]);
+    }
+
+    #[test]
+    fn tree_set_many() {
+        let mut next = xorshift(0x2545F491);
+        for sets in [1, 10, 100, 1000] {
+            let mut base = Tree::from(&[1, 1, 0]).unwrap();
+            for _ in 0..sets {
+                base.set(next() % base.bits());
+            }
+            for _ in 0..(sets / 4) {
+                base.unset(next() % base.bits());
+            }
+
+            let bits: Vec<usize> = (0..sets).map(|_| next() % 20000).collect();
+            let mut expected = base.clone();
+            expected.grow_to(bits.iter().max().unwrap() + 1);
+            for bit in bits.iter() {
+                expected.set(*bit);
+            }
+
+            let mut tree = base.clone();
+            tree.set_many(&bits);
+            assert_eq!(expected, tree);
+        }
+
+        let mut tree = Tree::new();
+        tree.set_many(&[]);
+        assert_eq!(Tree::new(), tree);
+
+        tree.set_many(&[4095, 3, 4095, 17]);
+        let mut expected = Tree::new();
+        expected.extend([4095, 3, 17]);
+        assert_eq!(expected, tree);
+        assert_eq!(vec![3, 17, 4095], tree.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tree_append() {
+        let mut tree = Tree::from_ones([1, 15]);
+        let shard = Tree::from_ones([0, 3, 17]);
+
+        tree.append(&shard);
+        assert_eq!(
+            vec![1, 15, 16, 19, 33],
+            tree.iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(2, tree.height());
+
+        tree.append_at(1000, &shard);
+        assert_eq!(
+            vec![1, 15, 16, 19, 33, 1000, 1003, 1017],
+            tree.iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(3, tree.height());
+
+        tree.append(&Tree::new());
+        assert_eq!(8, tree.count_ones());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_append_overflow() {
+        Tree::new().append_at(usize::MAX, &Tree::from_ones([1]));
+    }
+
+    #[test]
+    fn tree_first_last_one() {
+        assert_eq!(
+            (None, None),
+            (Tree::new().first_one(), Tree::new().last_one())
+        );
+        let tree = Tree::with_height(3);
+        assert_eq!((None, None), (tree.first_one(), tree.last_one()));
+
+        let mut next = xorshift(0x2545F491);
+        for sets in [1, 10, 100, 1000] {
+            let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+            for _ in 0..sets {
+                tree.set(next() % tree.bits());
+            }
+            for _ in 0..(sets / 4) {
+                tree.unset(next() % tree.bits());
+            }
+            assert_eq!(tree.iter_ones().next(), tree.first_one());
+            assert_eq!(tree.iter_ones().last(), tree.last_one());
+        }
+
+        // unset bits leave empty leaf Cells at both ends.
+        let mut tree = Tree::from_ones([1, 20, 300, 4095]);
+        tree.unset(1);
+        tree.unset(4095);
+        assert_eq!((Some(20), Some(300)), (tree.first_one(), tree.last_one()));
+    }
+
+    #[test]
+    fn tree_next_one() {
+        let mut tree = Tree::from_ones([0, 1, 15, 16, 17, 31, 255, 256, 3000, 4095]);
+        tree.unset(3000);
+
+        let mut ones = vec![];
+        let mut cursor = tree.first_one();
+        while let Some(bit) = cursor {
+            ones.push(bit);
+            cursor = tree.next_one(bit);
+        }
+        assert_eq!(tree.iter_ones().collect::<Vec<_>>(), ones);
+
+        for after in [2, 18, 32, 254, 257, 2999, 3000, 4094] {
+            assert_eq!(tree.iter_ones_from(after + 1).next(), tree.next_one(after));
+        }
+        assert_eq!(None, tree.next_one(4095));
+        assert_eq!(None, tree.next_one(usize::MAX));
+        assert_eq!(None, Tree::with_height(3).next_one(0));
+
+        let mut rand = xorshift(0x5eed);
+        let tree: Tree<u8> = (0..200).map(|_| rand() % 100_000).collect();
+        for after in (0..tree.bits()).step_by(97) {
+            assert_eq!(tree.iter_ones_from(after + 1).next(), tree.next_one(after));
+        }
+    }
+
+    #[test]
+    fn tree_prune() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        let bits = [0, 17, 255, 256, 1000, 2049, 4095];
+        for idx in bits {
+            tree.set(idx);
+        }
+        let before = tree.clone();
+
+        tree.prune();
+        assert_eq!(before, tree);
+
+        for idx in bits {
+            tree.unset(idx);
+        }
+        assert_ne!(Tree::new().to_vec(), tree.to_vec());
+
+        tree.prune();
+        assert_eq!(3, tree.height());
+        assert_eq!(Tree::new().to_vec(), tree.to_vec());
+        assert_eq!(Tree::with_height(3), tree);
+    }
+
+    #[test]
+    fn tree_prune_partial() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in 0..tree.bits() {
+            tree.set(idx);
+        }
+        for idx in (0..tree.bits()).filter(|idx| idx % 300 != 0) {
+            tree.unset(idx);
+        }
+
+        tree.prune();
+        let ones: Vec<usize> = tree.iter_ones().collect();
+        assert_eq!((0..4096).step_by(300).collect::<Vec<_>>(), ones);
+        assert_eq!(<Tree>::from_sorted_ones(3, ones).to_vec(), tree.to_vec());
+    }
+
+    #[test]
+    fn tree_retain_range() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in (0..tree.bits()).step_by(100) {
+            tree.set(idx);
+        }
+
+        tree.retain_range(250..1001);
+        assert_eq!(4096, tree.bits());
+        assert_eq!(
+            (300..=1000).step_by(100).collect::<Vec<_>>(),
+            tree.iter_ones().collect::<Vec<_>>()
+        );
+
+        tree.retain_range(900..usize::MAX);
+        assert_eq!(vec![900, 1000], tree.iter_ones().collect::<Vec<_>>());
+
+        tree.retain_range(0..0);
+        assert_eq!(None, tree.iter_ones().next());
+    }
+
+    #[test]
+    fn tree_retain() {
+        let mut tree = Tree::from_ones([1, 2, 3, 16, 17, 40, 41, 4000]);
+        tree.unset(3);
+
+        let mut seen = vec![];
+        tree.retain(|bit| {
+            seen.push(bit);
+            bit % 2 == 0
+        });
+        assert_eq!(vec![1, 2, 16, 17, 40, 41, 4000], seen);
+        assert_eq!(Tree::from_sorted_ones(3, [2, 16, 40, 4000]), tree);
+
+        tree.retain(|bit| bit < 100);
+        assert_eq!(Tree::from_sorted_ones(3, [2, 16, 40]), tree);
+
+        tree.retain(|_| false);
+        assert_eq!(Tree::with_height(3), tree);
+    }
+
+    #[test]
+    fn tree_split_at() {
+        let mut tree = Tree::from_ones([0, 3, 16, 17, 19, 31, 200, 4095]);
+        tree.unset(31);
+
+        for bit in [0, 1, 16, 18, 19, 20, 32, 200, 4095, 4096, 10000] {
+            let (below, above) = tree.split_at(bit);
+            assert_eq!(tree.height(), below.height());
+            assert_eq!(tree.height(), above.height());
+            assert!(below.iter_ones().all(|one| one < bit));
+            assert!(above.iter_ones().all(|one| one >= bit));
+            assert_eq!(tree.pruned(), below.union(&above));
+        }
+
+        let (below, above) = tree.split_at(18);
+        assert_eq!(vec![0, 3, 16, 17], below.iter_ones().collect::<Vec<_>>());
+        assert_eq!(vec![19, 200, 4095], above.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tree_shift() {
+        let tree = Tree::from_ones([0, 3, 17, 200]);
+
+        let right = tree.shift(10);
+        assert_eq!(vec![10, 13, 27, 210], right.iter_ones().collect::<Vec<_>>());
+        assert_eq!(tree.height(), right.height());
+
+        let grown = tree.shift(4000);
+        assert_eq!(
+            vec![4000, 4003, 4017, 4200],
+            grown.iter_ones().collect::<Vec<_>>()
+        );
+        assert_eq!(4, grown.height());
+
+        let left = tree.shift(-3);
+        assert_eq!(vec![0, 14, 197], left.iter_ones().collect::<Vec<_>>());
+        assert_eq!(tree.height(), left.height());
+
+        assert_eq!(Tree::with_height(2), tree.shift(-201));
+        assert_eq!(tree, tree.shift(0));
+        assert!(tree.semantically_eq(&tree.shift(4000).shift(-4000)));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_shift_overflow() {
+        Tree::from_ones([17]).shift(isize::MAX);
+    }
+
+    #[test]
+    fn tree_leading_trailing_zeros() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        assert_eq!(4096, tree.leading_zeros());
+        assert_eq!(4096, tree.trailing_zeros());
+
+        tree.set(700);
+        assert_eq!(4095 - 700, tree.leading_zeros());
+        assert_eq!(700, tree.trailing_zeros());
+
+        tree.set(33);
+        tree.set(2050);
+        assert_eq!(4095 - 2050, tree.leading_zeros());
+        assert_eq!(33, tree.trailing_zeros());
+
+        tree.set(0);
+        tree.set(4095);
+        assert_eq!(0, tree.leading_zeros());
+        assert_eq!(0, tree.trailing_zeros());
+
+        // unset bits leave empty cells behind, which must be skipped.
+        tree.unset(0);
+        tree.unset(4095);
+        assert_eq!(4095 - 2050, tree.leading_zeros());
+        assert_eq!(33, tree.trailing_zeros());
+    }
+
     #[test]
     fn tree_mega_unset() {
         let mut tree = Tree::from(&[1, 1, 0]).unwrap();
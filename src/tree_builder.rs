@@ -0,0 +1,139 @@
+// {{{ Copyright (c) Paul R. Tagliamonte <paultag@gmail.com>, 2024
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE. }}}
+
+use super::{
+    CellInt, CellRepr, Tree,
+    std::{marker::PhantomData, vec::Vec},
+};
+
+/// A [TreeBuilder] collects the bits to set in a new [Tree], and then
+/// builds the [Tree] all at once with [TreeBuilder::build]. Rather than
+/// growing the [Tree] and inserting Cells into each layer with every
+/// [Tree::set], the final height is computed once, and each layer is laid
+/// out in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeBuilder<T: CellInt = CellRepr> {
+    indices: Vec<usize>,
+    _cell: PhantomData<T>,
+}
+
+impl<T: CellInt> Default for TreeBuilder<T> {
+    /// Create a new [TreeBuilder] with no bits set. See [TreeBuilder::new].
+    fn default() -> Self {
+        Self {
+            indices: Vec::new(),
+            _cell: PhantomData,
+        }
+    }
+}
+
+impl TreeBuilder {
+    /// Create a new [TreeBuilder] with no bits set, for a [Tree] of the
+    /// default `u16` Cells. For other [CellInt] types, use
+    /// [Default::default].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: CellInt> TreeBuilder<T> {
+    /// Add a bit to be set in the [Tree]. Bits may be pushed in any order,
+    /// and pushing the same bit more than once has no further effect.
+    pub fn push(&mut self, index: usize) {
+        self.indices.push(index);
+    }
+
+    /// Return the number of bits pushed so far, including any duplicates.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Return true if no bits have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Build a [Tree] with only the pushed bits set, which is just tall
+    /// enough to hold the largest of them, like [Tree::from_ones]. The
+    /// resulting [Tree] will not contain any empty Cells. If the largest
+    /// bit is beyond the largest capacity representable by a `usize` (see
+    /// [Tree::grow_to]), a panic will be triggered.
+    pub fn build(self) -> Tree<T> {
+        self.indices.into_iter().collect()
+    }
+}
+
+impl<T: CellInt> Extend<usize> for TreeBuilder<T> {
+    /// [TreeBuilder::push] each of the provided bits.
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        self.indices.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::vec;
+
+    #[test]
+    fn tree_builder() {
+        let mut builder = TreeBuilder::new();
+        assert!(builder.is_empty());
+        for bit in [4000, 17, 3, 17, 1] {
+            builder.push(bit);
+        }
+        assert_eq!(5, builder.len());
+
+        let tree = builder.build();
+        assert_eq!(Tree::from_ones([1, 3, 17, 4000]), tree);
+
+        let mut set = Tree::new();
+        set.grow_to(4001);
+        for bit in [4000, 17, 3, 1] {
+            set.set(bit);
+        }
+        assert_eq!(set, tree);
+    }
+
+    #[test]
+    fn tree_builder_empty() {
+        assert_eq!(Tree::new(), TreeBuilder::new().build());
+    }
+
+    #[test]
+    fn tree_builder_u64() {
+        let mut builder = TreeBuilder::<u64>::default();
+        builder.extend([64, 4095, 0]);
+
+        let tree = builder.build();
+        assert_eq!(2, tree.height());
+        assert_eq!(vec![0, 64, 4095], tree.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_builder_overflow() {
+        let mut builder = TreeBuilder::new();
+        builder.push(usize::MAX);
+        builder.build();
+    }
+}
+
+// vim: foldmethod=marker
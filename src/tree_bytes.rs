@@ -0,0 +1,698 @@
+// {{{ Copyright (c) Paul R. Tagliamonte <paultag@gmail.com>, 2024
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE. }}}
+
+use super::{
+    CellRepr, Error, Tree,
+    std::{mem::size_of, vec, vec::Vec},
+};
+
+#[cfg(feature = "std")]
+use super::std::io::{self, ErrorKind, Read, Write};
+
+/// Number of bytes used to encode a single Cell.
+const CELL_BYTES: usize = size_of::<CellRepr>();
+
+/// Magic bytes at the start of [Tree::write_with_header].
+#[cfg(feature = "std")]
+const HEADER_MAGIC: [u8; 4] = *b"K2SQ";
+
+/// Version of the layout following the header of [Tree::write_with_header].
+#[cfg(feature = "std")]
+const HEADER_VERSION: u8 = 1;
+
+impl Tree {
+    /// Construct a new K2 [Tree] from a big-endian byte stream of `u16`
+    /// Cells, like [Tree::from]. An empty stream will return an
+    /// [Error::Empty], and a stream which is not a whole number of Cells, or
+    /// does not describe a valid [Tree], will return an [Error::Malformed].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Tree::from(&cells_from_be_bytes(bytes)?)
+    }
+
+    /// Turn the tree into a big-endian byte stream of every Cell from
+    /// [Tree::to_vec], two bytes per Cell. This can be loaded again with
+    /// [Tree::from_bytes]. This is the same layout `Tree::write_to` streams
+    /// out with the `std` feature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        cells_to_be_bytes(&self.to_vec())
+    }
+
+    /// Turn the tree into a big-endian byte stream, prefixed with the height
+    /// of the [Tree] (as a big-endian `u16`). Unlike the bare Cells from
+    /// [Tree::to_vec], this will restore the exact same [Tree::bits] when
+    /// loaded with [Tree::from_bytes_with_len], even when the upper layers
+    /// of the tree are empty.
+    pub fn to_bytes_with_len(&self) -> Vec<u8> {
+        let height = CellRepr::try_from(self.height()).expect("tree height overflows header");
+
+        let mut ret = height.to_be_bytes().to_vec();
+        ret.append(&mut self.to_bytes());
+        ret
+    }
+
+    /// Construct a new K2 [Tree] from the output of [Tree::to_bytes_with_len].
+    /// If the encoded height doesn't agree with the encoded Cells, or is too
+    /// tall for [Tree::bits] to fit in a `usize`, this will return an
+    /// [Error::Malformed].
+    pub fn from_bytes_with_len(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let cells = cells_from_be_bytes(bytes)?;
+        Tree::from_cells_with_len(cells[0] as usize, &cells[1..])
+    }
+
+    /// Construct a new K2 [Tree] from the decoded height header and Cells of
+    /// [Tree::to_bytes_with_len].
+    fn from_cells_with_len(height: usize, cells: &[CellRepr]) -> Result<Self, Error> {
+        if height == 0 || height > Self::max_height() {
+            return Err(Error::Malformed);
+        }
+        let tree = Tree::from(cells)?;
+
+        if tree.height() == height {
+            return Ok(tree);
+        }
+
+        // an empty tree is encoded as a single Cell, no matter the height.
+        if cells == [0] && height > 1 {
+            return Ok(Tree::with_height(height));
+        }
+
+        Err(Error::Malformed)
+    }
+
+    /// Encode the tree for use as a protobuf `bytes` field.
+    ///
+    /// The layout is exactly [Tree::to_bytes]: the Cells from [Tree::to_vec],
+    /// in order, each as a big-endian `u16`, with no header or padding:
+    ///
+    /// ```text
+    /// +-----------+-----------+-----+-------------+
+    /// | cell 0 BE | cell 1 BE | ... | cell N-1 BE |
+    /// | (2 bytes) | (2 bytes) |     | (2 bytes)   |
+    /// +-----------+-----------+-----+-------------+
+    /// ```
+    ///
+    /// Since the protobuf `bytes` type is already length-delimited, no
+    /// additional length is encoded.
+    pub fn to_proto_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    /// Construct a new K2 [Tree] from the output of [Tree::to_proto_bytes].
+    /// This is the same as [Tree::from_bytes].
+    pub fn from_proto_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Tree::from_bytes(bytes)
+    }
+
+    /// Turn the tree into a run-length encoded byte stream of the Cells from
+    /// [Tree::to_vec]. Each run of identical consecutive Cells is encoded as
+    /// the number of Cells in the run (as an unsigned LEB128 varint, never
+    /// `0`), followed by the Cell as a big-endian `u16`:
+    ///
+    /// ```text
+    /// +--------------+-----------+--------------+-----------+-----+
+    /// | run 0 length | run 0 BE  | run 1 length | run 1 BE  | ... |
+    /// | (varint)     | (2 bytes) | (varint)     | (2 bytes) |     |
+    /// +--------------+-----------+--------------+-----------+-----+
+    /// ```
+    ///
+    /// Like [Tree::to_bytes], the stream is self-describing, so no
+    /// additional header is written. This can be loaded again with
+    /// [Tree::from_compressed].
+    pub fn to_compressed(&self) -> Vec<u8> {
+        let mut ret = vec![];
+        let cells = self.to_vec();
+        for run in cells.chunk_by(|a, b| a == b) {
+            write_varint(&mut ret, run.len());
+            ret.extend(run[0].to_be_bytes());
+        }
+        ret
+    }
+
+    /// Construct a new K2 [Tree] from the output of [Tree::to_compressed].
+    /// An empty stream will return an [Error::Empty], and a stream which ends
+    /// partway through a run, contains an empty run, or does not describe a
+    /// valid [Tree], will return an [Error::Malformed].
+    ///
+    /// Since a dense [Tree] compresses very well, the decoded [Tree] may be
+    /// far larger than `bytes`. Before any Cells are decoded, the runs are
+    /// checked against the layers of the [Tree] (each layer holds one Cell
+    /// per bit set in the layer above it), so a run which goes past the
+    /// end of the [Tree], or past [Tree::max_height] layers, will return an
+    /// [Error::Malformed] without allocating the Cells it describes.
+    pub fn from_compressed(mut bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let mut runs = vec![];
+        while !bytes.is_empty() {
+            let len = read_varint(&mut bytes)?;
+            if len == 0 || bytes.len() < CELL_BYTES {
+                return Err(Error::Malformed);
+            }
+            let (cell, rest) = bytes.split_at(CELL_BYTES);
+            bytes = rest;
+            runs.push((len, CellRepr::from_be_bytes(cell.try_into().unwrap())));
+        }
+
+        let len = compressed_len(&runs)?;
+        let mut cells = Vec::new();
+        cells.try_reserve_exact(len).map_err(|_| Error::Malformed)?;
+        for (len, cell) in runs {
+            cells.resize(cells.len() + len, cell);
+        }
+        Tree::from(&cells)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Tree {
+    /// Write the [Tree] to `w`, in the same layout as [Tree::to_bytes]
+    /// (every Cell from [Tree::to_vec], as a big-endian `u16`). This can be
+    /// read back in with [Tree::read_from], or [Tree::from_bytes].
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for cell in self.to_vec() {
+            w.write_all(&cell.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read a [Tree] written by [Tree::write_to] (or [Tree::to_bytes]) from
+    /// `r`, one layer at a time. Each layer describes how many Cells the next
+    /// layer holds, so this reads until `r` is at EOF at the end of a layer,
+    /// or a layer has no bits set (in which case anything after it is left
+    /// in `r`).
+    ///
+    /// A stream which is empty will return an [Error::Empty], one which
+    /// ends partway through a Cell or layer, or is too tall for
+    /// [Tree::bits] to fit in a `usize`, will return an [Error::Malformed],
+    /// and any I/O failure will return an [Error::Io].
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Tree, Error> {
+        let mut cells = vec![read_cell(r)?.ok_or(Error::Empty)?];
+        let mut layer = 0..1;
+        for height in 1.. {
+            let layer_len: usize = cells[layer.clone()]
+                .iter()
+                .map(|cell| cell.count_ones() as usize)
+                .sum();
+            if layer_len == 0 {
+                break;
+            }
+
+            let Some(first) = read_cell(r)? else {
+                break;
+            };
+            if height == Self::max_height() {
+                return Err(Error::Malformed);
+            }
+
+            layer = cells.len()..(cells.len() + layer_len);
+            cells.push(first);
+            for _ in 1..layer_len {
+                cells.push(read_cell(r)?.ok_or(Error::Malformed)?);
+            }
+        }
+
+        Tree::from(&cells)
+    }
+
+    /// Write the [Tree] to `w` like [Tree::write_to], but prefixed with a
+    /// small header describing the format, so a reader can tell when it was
+    /// written with a different layout or Cell width:
+    ///
+    /// ```text
+    /// +-----------+-----------+------------+------------------------+
+    /// | "K2SQ"    | version   | cell bits  | Tree::write_to payload |
+    /// | (4 bytes) | (1 byte)  | (1 byte)   |                        |
+    /// +-----------+-----------+------------+------------------------+
+    /// ```
+    ///
+    /// This can be read back in with [Tree::read_with_header]. The
+    /// headerless [Tree::write_to] is unchanged.
+    pub fn write_with_header<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&HEADER_MAGIC)?;
+        w.write_all(&[HEADER_VERSION, CellRepr::BITS as u8])?;
+        self.write_to(w)
+    }
+
+    /// Read a [Tree] written by [Tree::write_with_header] from `r`. If the
+    /// magic bytes, version or Cell width of the header don't match this
+    /// version of the crate, an [Error::Malformed] is returned; otherwise
+    /// this behaves like [Tree::read_from].
+    pub fn read_with_header<R: Read>(r: &mut R) -> Result<Tree, Error> {
+        read_header(r, CellRepr::BITS as u8)?;
+        Tree::read_from(r)
+    }
+}
+
+/// Read and check the header written by [Tree::write_with_header], which
+/// must describe Cells of `cell_bits` bits.
+#[cfg(feature = "std")]
+fn read_header<R: Read>(r: &mut R, cell_bits: u8) -> Result<(), Error> {
+    let mut header = [0; HEADER_MAGIC.len() + 2];
+    r.read_exact(&mut header).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => Error::Malformed,
+        _ => Error::Io,
+    })?;
+
+    if header[..HEADER_MAGIC.len()] != HEADER_MAGIC
+        || header[HEADER_MAGIC.len()..] != [HEADER_VERSION, cell_bits]
+    {
+        return Err(Error::Malformed);
+    }
+    Ok(())
+}
+
+/// Read a single big-endian Cell from `r`, returning `None` if `r` is
+/// already at EOF, or an [Error::Malformed] if `r` ends partway through
+/// the Cell.
+#[cfg(feature = "std")]
+fn read_cell<R: Read>(r: &mut R) -> Result<Option<CellRepr>, Error> {
+    let mut buf = [0; CELL_BYTES];
+    let mut read = 0;
+    while read < CELL_BYTES {
+        match r.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(Error::Malformed),
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return Err(Error::Io),
+        }
+    }
+    Ok(Some(CellRepr::from_be_bytes(buf)))
+}
+
+/// Walk the layers described by the runs of [Tree::to_compressed], without
+/// decoding them, returning the total number of Cells. If a run goes past
+/// the end of the last layer, the [Tree] would be taller than
+/// [Tree::max_height], or the runs end partway through a layer, an
+/// [Error::Malformed] is returned.
+fn compressed_len(runs: &[(usize, CellRepr)]) -> Result<usize, Error> {
+    let mut total: usize = 0;
+    let mut height = 1;
+    // Cells left to fill in the current layer, and the number of Cells the
+    // next layer holds (one per bit set in the current layer).
+    let (mut remaining, mut next): (usize, usize) = (1, 0);
+
+    for (len, cell) in runs {
+        let mut len = *len;
+        while len > 0 {
+            if remaining == 0 {
+                if next == 0 || height == <Tree>::max_height() {
+                    return Err(Error::Malformed);
+                }
+                (remaining, next) = (next, 0);
+                height += 1;
+            }
+
+            let n = len.min(remaining);
+            next = (cell.count_ones() as usize)
+                .checked_mul(n)
+                .and_then(|ones| next.checked_add(ones))
+                .ok_or(Error::Malformed)?;
+            total += n;
+            remaining -= n;
+            len -= n;
+        }
+    }
+
+    if remaining != 0 {
+        return Err(Error::Malformed);
+    }
+    Ok(total)
+}
+
+/// Append `n` to `ret` as an unsigned LEB128 varint.
+fn write_varint(ret: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        ret.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    ret.push(n as u8);
+}
+
+/// Read an unsigned LEB128 varint from the front of `bytes`, advancing past
+/// it. If `bytes` ends partway through the varint, or the varint doesn't fit
+/// in a `usize`, an [Error::Malformed] is returned.
+fn read_varint(bytes: &mut &[u8]) -> Result<usize, Error> {
+    let mut n: usize = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(Error::Malformed)?;
+        *bytes = rest;
+
+        let value = (byte & 0x7F) as usize;
+        if value.checked_shl(shift).map(|v| v >> shift) != Some(value) {
+            return Err(Error::Malformed);
+        }
+        n |= value << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(Error::Malformed)
+}
+
+/// Encode a set of Cells as a stream of big-endian Cells.
+fn cells_to_be_bytes(cells: &[CellRepr]) -> Vec<u8> {
+    cells.iter().flat_map(|cell| cell.to_be_bytes()).collect()
+}
+
+/// Decode a stream of big-endian Cells, returning an [Error::Malformed] if
+/// the stream doesn't contain a whole number of Cells.
+fn cells_from_be_bytes(bytes: &[u8]) -> Result<Vec<CellRepr>, Error> {
+    let chunks = bytes.chunks_exact(CELL_BYTES);
+    if !chunks.remainder().is_empty() {
+        return Err(Error::Malformed);
+    }
+    Ok(chunks
+        .map(|chunk| CellRepr::from_be_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::vec;
+
+    #[test]
+    fn tree_from_bytes() {
+        let tree = Tree::from_bytes(&[0, 3, 0, 10, 0, 10]).unwrap();
+        assert_eq!(Tree::from(&[3, 10, 10]).unwrap(), tree);
+        assert_eq!(vec![1, 3, 17, 19], tree.iter_ones().collect::<Vec<_>>());
+        assert_eq!(tree, Tree::from_bytes(&tree.to_proto_bytes()).unwrap());
+    }
+
+    #[test]
+    fn tree_to_bytes() {
+        let mut tree = Tree::from_ones([17, 19, 4095]);
+        tree.unset(19);
+
+        let bytes = tree.to_bytes();
+        assert_eq!(tree.to_vec().len() * 2, bytes.len());
+        assert_eq!(vec![0x80, 0x01, 0x00, 0x02], bytes[..4]);
+        assert_eq!(tree, Tree::from_bytes(&bytes).unwrap());
+        assert_eq!(tree.to_proto_bytes(), bytes);
+
+        let tree = Tree::new();
+        assert_eq!(vec![0, 0], tree.to_bytes());
+        assert_eq!(tree, Tree::from_bytes(&tree.to_bytes()).unwrap());
+
+        assert_eq!(Err(Error::Empty), Tree::from_bytes(&[]));
+        assert_eq!(Err(Error::Malformed), Tree::from_bytes(&[0, 3, 0, 10, 0]));
+        assert_eq!(Err(Error::Malformed), Tree::from_bytes(&[0, 3, 0, 10]));
+    }
+
+    #[test]
+    fn tree_proto_bytes() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        tree.set(17);
+        tree.set(4000);
+
+        let bytes = tree.to_proto_bytes();
+        assert_eq!(vec![0x80, 0x01, 0x00, 0x03, 0x04, 0x00], bytes[0..6]);
+        assert_eq!(tree.to_vec().len() * 2, bytes.len());
+        assert_eq!(tree, Tree::from_proto_bytes(&bytes).unwrap());
+
+        assert_eq!(Err(Error::Empty), Tree::from_proto_bytes(&[]));
+        assert_eq!(Err(Error::Malformed), Tree::from_proto_bytes(&[0, 1, 0]));
+        assert_eq!(Err(Error::Malformed), Tree::from_proto_bytes(&[0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn tree_compressed_dense() {
+        let mut tree = Tree::with_height(3);
+        tree.set_many(&(0..tree.bits()).collect::<Vec<_>>());
+        assert_eq!(273, tree.to_vec().len());
+
+        let bytes = tree.to_compressed();
+        assert_eq!(vec![0x91, 0x02, 0xFF, 0xFF], bytes);
+        assert_eq!(tree, Tree::from_compressed(&bytes).unwrap());
+    }
+
+    #[test]
+    fn tree_compressed_sparse() {
+        let mut tree = Tree::from_ones([17, 19, 4000, 1 << 20]);
+        tree.unset(4000);
+
+        let bytes = tree.to_compressed();
+        assert_eq!(
+            tree.to_vec(),
+            Tree::from_compressed(&bytes).unwrap().to_vec()
+        );
+        assert_eq!(tree, Tree::from_compressed(&bytes).unwrap());
+
+        let tree = Tree::new();
+        assert_eq!(vec![1, 0, 0], tree.to_compressed());
+        assert_eq!(tree, Tree::from_compressed(&tree.to_compressed()).unwrap());
+    }
+
+    #[test]
+    fn tree_compressed_malformed() {
+        assert_eq!(Err(Error::Empty), Tree::from_compressed(&[]));
+        assert_eq!(Err(Error::Malformed), Tree::from_compressed(&[1, 0]));
+        assert_eq!(Err(Error::Malformed), Tree::from_compressed(&[0, 0, 0]));
+        assert_eq!(Err(Error::Malformed), Tree::from_compressed(&[0x80]));
+        assert_eq!(Err(Error::Malformed), Tree::from_compressed(&[2, 0, 0]));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&[
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0, 0
+            ])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&[1, 0, 3, 1, 0, 10, 2])
+        );
+    }
+
+    #[test]
+    fn tree_compressed_oversized_run() {
+        let compressed = |runs: &[(usize, CellRepr)]| {
+            let mut ret = vec![];
+            for (len, cell) in runs {
+                write_varint(&mut ret, *len);
+                ret.extend(cell.to_be_bytes());
+            }
+            ret
+        };
+
+        let bytes = compressed(&[(1 << 62, 0)]);
+        assert_eq!(11, bytes.len());
+        assert_eq!(Err(Error::Malformed), Tree::from_compressed(&bytes));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&compressed(&[(usize::MAX, 0xFFFF)]))
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&compressed(&[(1, 3), (1 << 40, 0)]))
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&compressed(&[(1 << 50, 0xFFFF)]))
+        );
+
+        // a single run of full Cells is a full Tree, if it ends on the last
+        // Cell of a layer.
+        let tree = Tree::from_compressed(&compressed(&[(1 + 16 + 256, 0xFFFF)])).unwrap();
+        assert_eq!(4096, tree.count_ones());
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&compressed(&[(1 + 16 + 255, 0xFFFF)]))
+        );
+
+        // a chain of Cells with only the lowest bit set is one layer taller
+        // per Cell.
+        let height = <Tree>::max_height();
+        let tree = Tree::from_compressed(&compressed(&[(height, 1)])).unwrap();
+        assert_eq!(height, tree.height());
+        assert!(tree.get(0));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_compressed(&compressed(&[(height + 1, 1)]))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tree_write_read() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+
+        let mut out = vec![];
+        tree.write_to(&mut out).unwrap();
+        assert_eq!(tree.to_bytes(), out);
+        assert_eq!(tree, Tree::read_from(&mut out.as_slice()).unwrap());
+        assert_eq!(tree, Tree::from_bytes(&out).unwrap());
+
+        let mut r = tree.to_bytes_with_len();
+        r.drain(..2);
+        assert_eq!(tree, Tree::read_from(&mut r.as_slice()).unwrap());
+
+        assert_eq!(Err(Error::Empty), Tree::read_from(&mut [].as_slice()));
+        assert_eq!(Err(Error::Malformed), Tree::read_from(&mut &out[..1]));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::read_from(&mut &out[..out.len() - 1])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::read_from(&mut &out[..out.len() - 2])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::read_from(&mut [0xff, 0xff, 0, 0].as_slice())
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tree_write_read_empty_layer() {
+        let second = Tree::with_height(3);
+        let third = Tree::from_ones([2, 200]);
+
+        let mut out = vec![];
+        Tree::new().write_to(&mut out).unwrap();
+        second.write_to(&mut out).unwrap();
+        third.write_to(&mut out).unwrap();
+        assert_eq!(vec![0, 0, 0, 0], out[..4]);
+
+        // a layer with no bits set ends the tree, leaving the rest of `r`.
+        let mut r = out.as_slice();
+        assert_eq!(Tree::new(), Tree::read_from(&mut r).unwrap());
+        assert_eq!(Tree::new(), Tree::read_from(&mut r).unwrap());
+        assert_eq!(third.to_bytes(), r);
+        assert_eq!(third, Tree::read_from(&mut r).unwrap());
+        assert!(r.is_empty());
+        assert_eq!(Err(Error::Empty), Tree::read_from(&mut r));
+
+        let mut tree = Tree::from_ones([3, 200]);
+        tree.unset(3);
+        tree.unset(200);
+        let mut out = vec![];
+        tree.write_to(&mut out).unwrap();
+        out.extend_from_slice(&[0xff, 0xff]);
+
+        let mut r = out.as_slice();
+        assert_eq!(tree, Tree::read_from(&mut r).unwrap());
+        assert_eq!([0xff, 0xff], r);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tree_write_read_header() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+
+        let mut out = vec![];
+        tree.write_with_header(&mut out).unwrap();
+        assert_eq!(b"K2SQ\x01\x10", &out[..6]);
+        assert_eq!(tree.to_bytes(), out[6..]);
+
+        let mut r = out.as_slice();
+        assert_eq!(tree, Tree::read_with_header(&mut r).unwrap());
+        assert!(r.is_empty());
+
+        // the header is rejected by a reader expecting other Cells.
+        assert_eq!(Err(Error::Malformed), read_header(&mut out.as_slice(), 64));
+
+        for (idx, value) in [(0, b'X'), (4, 2), (5, 64)] {
+            let mut bad = out.clone();
+            bad[idx] = value;
+            assert_eq!(
+                Err(Error::Malformed),
+                Tree::read_with_header(&mut bad.as_slice())
+            );
+        }
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::read_with_header(&mut &out[..4])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::read_with_header(&mut tree.to_bytes().as_slice())
+        );
+    }
+
+    #[test]
+    fn tree_bytes_with_len() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        tree.set(17);
+        tree.set(4000);
+
+        let bytes = tree.to_bytes_with_len();
+        assert_eq!(vec![0, 3], bytes[0..2]);
+        assert_eq!(tree.to_vec().len() * 2 + 2, bytes.len());
+        assert_eq!(tree, Tree::from_bytes_with_len(&bytes).unwrap());
+    }
+
+    #[test]
+    fn tree_bytes_with_len_empty_height() {
+        let tree = Tree::with_height(4);
+        let bytes = tree.to_bytes_with_len();
+        assert_eq!(vec![0, 4, 0, 0], bytes);
+
+        let tree = Tree::from_bytes_with_len(&bytes).unwrap();
+        assert_eq!(4, tree.height());
+        assert_eq!(65536, tree.bits());
+    }
+
+    #[test]
+    fn tree_bytes_with_len_malformed() {
+        assert_eq!(Err(Error::Empty), Tree::from_bytes_with_len(&[]));
+        assert_eq!(Err(Error::Malformed), Tree::from_bytes_with_len(&[0, 1, 0]));
+        assert_eq!(Err(Error::Empty), Tree::from_bytes_with_len(&[0, 1]));
+
+        // height disagrees with the cells.
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_bytes_with_len(&[0, 2, 0, 1, 0, 1, 0, 1])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_bytes_with_len(&[0, 5, 0, 1, 0, 1])
+        );
+
+        // height too tall for `bits()` to fit in a usize.
+        let height = <Tree>::max_height() as u16 + 1;
+        let mut bytes = height.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[0, 0]);
+        assert_eq!(Err(Error::Malformed), Tree::from_bytes_with_len(&bytes));
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_bytes_with_len(&[0xff, 0xff, 0, 0])
+        );
+        assert_eq!(
+            Err(Error::Malformed),
+            Tree::from_bytes_with_len(&[0, 40, 0, 0])
+        );
+
+        let height = <Tree>::max_height() as u16;
+        let mut bytes = height.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[0, 0]);
+        let tree = Tree::from_bytes_with_len(&bytes).unwrap();
+        assert_eq!(<Tree>::max_height(), tree.height());
+        assert!(!tree.get(tree.bits() - 1));
+    }
+}
+
+// vim: foldmethod=marker
@@ -19,49 +19,140 @@
 // THE SOFTWARE. }}}
 
 use super::{
-    Cell, Tree,
-    std::{ops::Range, vec::Vec},
+    Cell, CellInt, CellRepr, Tree,
+    std::{cmp::Ordering, iter, ops::Range, vec, vec::Vec},
 };
 
-impl Tree {
+/// An iterator over the bits of a [Tree], returned by [Tree::iter] and
+/// [Tree::iter_range], or by iterating over a `&Tree`.
+pub struct Iter<T: CellInt = CellRepr>(LeafIterator<T, vec::IntoIter<(usize, Cell<T>)>>);
+
+impl<T: CellInt> Iterator for Iter<T> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T: CellInt> ExactSizeIterator for Iter<T> {}
+
+impl<T: CellInt> IntoIterator for &Tree<T> {
+    type Item = bool;
+    type IntoIter = Iter<T>;
+
+    /// Iterate over all the bits in the tree, like [Tree::iter].
+    fn into_iter(self) -> Iter<T> {
+        self.iter()
+    }
+}
+
+impl<T: CellInt> Tree<T> {
     /// Iterate over all the bits in the tree. Once called, this will take
     /// a copy of the data in the [Tree], which means any changes to the tree
     /// during iteration will be ignored.
-    pub fn iter(&self) -> impl Iterator<Item = bool> {
-        self.iter_from_to(0, self.bits())
+    pub fn iter(&self) -> Iter<T> {
+        Iter(self.iter_from_to(0, self.bits()))
     }
 
     /// Iterate over all the set bits in the tree. Once called, this will take
     /// a copy of the data in the [Tree], which means any changes to the tree
-    /// during iteration will be ignored.
-    pub fn iter_ones(&self) -> impl Iterator<Item = usize> {
+    /// during iteration will be ignored. The set bits may also be iterated
+    /// from the highest bit down, using [DoubleEndedIterator].
+    pub fn iter_ones(&self) -> impl DoubleEndedIterator<Item = usize> {
         self.iter_ones_from_to(0, self.bits())
     }
 
     /// Iterate over a subset of the bits in the tree. Once called, this will
     /// take a copy of the data in the [Tree], which means any changes to the
     /// tree during iteration will be ignored.
-    pub fn iter_range(&self, range: Range<usize>) -> impl Iterator<Item = bool> {
-        self.iter_from_to(range.start, range.end)
+    pub fn iter_range(&self, range: Range<usize>) -> Iter<T> {
+        Iter(self.iter_from_to(range.start, range.end))
     }
 
     /// Iterate over a subset of the bits in the tree. Once called, this will
     /// take a copy of the data in the [Tree], which means any changes to the
     /// tree during iteration will be ignored.
-    pub fn iter_ones_range(&self, range: Range<usize>) -> impl Iterator<Item = usize> {
+    pub fn iter_ones_range(&self, range: Range<usize>) -> impl DoubleEndedIterator<Item = usize> {
         self.iter_ones_from_to(range.start, range.end)
     }
 
+    /// Iterate over the set bits in the tree at or after `start`, like
+    /// `iter_ones_range(start..self.bits())`. Once called, this will take a
+    /// copy of the data in the [Tree], which means any changes to the tree
+    /// during iteration will be ignored.
+    pub fn iter_ones_from(&self, start: usize) -> impl DoubleEndedIterator<Item = usize> {
+        self.iter_ones_from_to(start, self.bits())
+    }
+
+    /// Iterate over all the unset bits in the tree, up to [Tree::bits]. Once
+    /// called, this will take a copy of the data in the [Tree], which means
+    /// any changes to the tree during iteration will be ignored. For a
+    /// sparse [Tree], this yields nearly every bit, so
+    /// [Tree::iter_zeros_range] is usually more useful.
+    pub fn iter_zeros(&self) -> impl Iterator<Item = usize> {
+        self.iter_zeros_range(0..self.bits())
+    }
+
+    /// Iterate over the unset bits in a subset of the tree. Any part of the
+    /// range beyond [Tree::bits] is ignored. Once called, this will take a
+    /// copy of the data in the [Tree], which means any changes to the tree
+    /// during iteration will be ignored.
+    pub fn iter_zeros_range(&self, range: Range<usize>) -> impl Iterator<Item = usize> {
+        let end = range.end.min(self.bits());
+        let start = range.start.min(end);
+        self.iter_range(start..end)
+            .zip(start..end)
+            .filter_map(|(set, bit)| (!set).then_some(bit))
+    }
+
+    /// Yield the set bits of the tree which are also present in `sorted`,
+    /// by walking [Tree::iter_ones] and `sorted` together. This avoids
+    /// building a second [Tree] just to intersect against a list of indices.
+    ///
+    /// `sorted` *must* be sorted in ascending order; if it is not, bits which
+    /// are present in both may be silently skipped.
+    pub fn intersect_sorted<'a>(
+        &'a self,
+        sorted: impl Iterator<Item = usize> + 'a,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let mut ones = self.iter_ones().peekable();
+        let mut sorted = sorted.peekable();
+
+        iter::from_fn(move || {
+            loop {
+                let (one, other) = (*ones.peek()?, *sorted.peek()?);
+                match one.cmp(&other) {
+                    Ordering::Less => {
+                        ones.next();
+                    }
+                    Ordering::Greater => {
+                        sorted.next();
+                    }
+                    Ordering::Equal => {
+                        ones.next();
+                        sorted.next();
+                        return Some(one);
+                    }
+                }
+            }
+        })
+    }
+
     /// Dump cells until we catch up to the commanded 'from' value.
     fn _scan_iter_forward(
         &self,
-        mut iter: impl Iterator<Item = (usize, Cell)>,
+        mut iter: impl Iterator<Item = (usize, Cell<T>)>,
         from: usize,
-    ) -> Option<(usize, Cell)> {
+    ) -> Option<(usize, Cell<T>)> {
         loop {
             match iter.next() {
                 Some((offset, cell)) => {
-                    if offset + Cell::bits() >= from {
+                    if offset + Cell::<T>::bits() > from {
                         return Some((offset, cell));
                     }
                 }
@@ -73,20 +164,22 @@ impl Tree {
     }
 
     /// Return an iterator over the tree.
-    fn iter_from_to(&self, from: usize, to: usize) -> impl Iterator<Item = bool> {
+    fn iter_from_to(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> LeafIterator<T, vec::IntoIter<(usize, Cell<T>)>> {
         let leaf_layer = self.leaf_layer();
         let mut leaf_layer_iter = leaf_layer.clone().into_iter();
         let mut leaf_layer_cur = leaf_layer_iter.next();
 
-        if from >= Cell::bits() {
-            // here we need to scan forward until the leaf_layer_cur is
-            // gte from
-
-            if let Some((offset, _)) = leaf_layer_cur {
-                if (offset + Cell::bits()) <= from {
-                    leaf_layer_cur = self._scan_iter_forward(&mut leaf_layer_iter, from);
-                }
-            }
+        // here we need to scan forward until the leaf_layer_cur is
+        // gte from
+        if from >= Cell::<T>::bits()
+            && let Some((offset, _)) = leaf_layer_cur
+            && (offset + Cell::<T>::bits()) <= from
+        {
+            leaf_layer_cur = self._scan_iter_forward(&mut leaf_layer_iter, from);
         }
 
         LeafIterator {
@@ -100,49 +193,43 @@ impl Tree {
     }
 
     /// Return a ones iterator over the tree.
-    fn iter_ones_from_to(&self, from: usize, to: usize) -> impl Iterator<Item = usize> {
+    fn iter_ones_from_to(&self, from: usize, to: usize) -> impl DoubleEndedIterator<Item = usize> {
         let leaf_layer = self.leaf_layer();
-        let mut leaf_layer_iter = leaf_layer.clone().into_iter();
-        let mut leaf_layer_cur = leaf_layer_iter.next();
 
-        if from >= Cell::bits() {
-            if let Some((offset, _)) = leaf_layer_cur {
-                if (offset + Cell::bits()) <= from {
-                    leaf_layer_cur = self._scan_iter_forward(&mut leaf_layer_iter, from);
-                }
-            }
-        }
+        // skip over every cell which ends before the commanded 'from' value.
+        let front = leaf_layer.partition_point(|(offset, _)| offset + Cell::<T>::bits() <= from);
+        let back = leaf_layer.len();
 
         LeafIteratorOnes {
-            index: from.max(leaf_layer_cur.map(|(v, _)| v).unwrap_or(0)),
-            bits: to,
+            index: from,
+            end: to,
 
-            leaf_layer_cur,
-            leaf_layer_iter,
-            _leaf_layer: leaf_layer,
+            front,
+            back,
+            leaf_layer,
         }
     }
 }
 
 /// Iterator which takes a copy of the leaf layer, aligned with the starting
 /// offset(s) of the leaf Cell values.
-struct LeafIterator<IterT>
+struct LeafIterator<T: CellInt, IterT>
 where
-    IterT: Iterator<Item = (usize, Cell)>,
+    IterT: Iterator<Item = (usize, Cell<T>)>,
 {
     index: usize,
     bits: usize,
 
     // needed for ownership reasons
-    _leaf_layer: Vec<(usize, Cell)>,
+    _leaf_layer: Vec<(usize, Cell<T>)>,
 
-    leaf_layer_cur: Option<(usize, Cell)>,
+    leaf_layer_cur: Option<(usize, Cell<T>)>,
     leaf_layer_iter: IterT,
 }
 
-impl<IterT> Iterator for LeafIterator<IterT>
+impl<T: CellInt, IterT> Iterator for LeafIterator<T, IterT>
 where
-    IterT: Iterator<Item = (usize, Cell)>,
+    IterT: Iterator<Item = (usize, Cell<T>)>,
 {
     type Item = bool;
 
@@ -176,56 +263,85 @@ where
 
         // check to see if we need to get the next cell; if we've just
         // handled the last bit of the cell.
-        if self.index >= offset + Cell::bits() {
+        if self.index >= offset + Cell::<T>::bits() {
             self.leaf_layer_cur = self.leaf_layer_iter.next();
         }
 
         Some(value)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bits.saturating_sub(self.index);
+        (len, Some(len))
+    }
 }
 
-/// Iterator which takes a copy of the leaf layer, aligned with the starting
-/// offset(s) of the leaf Cell values.
-struct LeafIteratorOnes<IterT>
-where
-    IterT: Iterator<Item = (usize, Cell)>,
+impl<T: CellInt, IterT> ExactSizeIterator for LeafIterator<T, IterT> where
+    IterT: Iterator<Item = (usize, Cell<T>)>
 {
-    index: usize,
-    bits: usize,
+}
 
-    // needed for ownership reasons
-    _leaf_layer: Vec<(usize, Cell)>,
+/// Iterator which takes a copy of the leaf layer, aligned with the starting
+/// offset(s) of the leaf Cell values. Bits are yielded from the front of
+/// the range (`index`) and the back of the range (`end`), each of which
+/// tracks the leaf Cell it's currently within (`front` and `back`).
+struct LeafIteratorOnes<T: CellInt> {
+    index: usize,
+    end: usize,
 
-    leaf_layer_cur: Option<(usize, Cell)>,
-    leaf_layer_iter: IterT,
+    front: usize,
+    back: usize,
+    leaf_layer: Vec<(usize, Cell<T>)>,
 }
 
-impl<IterT> Iterator for LeafIteratorOnes<IterT>
-where
-    IterT: Iterator<Item = (usize, Cell)>,
-{
+impl<T: CellInt> Iterator for LeafIteratorOnes<T> {
     type Item = usize;
 
     fn next(&mut self) -> Option<usize> {
         loop {
-            if self.index >= self.bits {
+            if self.index >= self.end {
                 return None;
             }
-            let (offset, cell) = self.leaf_layer_cur?;
+            let (offset, cell) = *self.leaf_layer.get(self.front)?;
             if self.index < offset {
                 self.index = offset;
+                continue;
+            }
+            if self.index >= offset + Cell::<T>::bits() {
+                self.front += 1;
+                continue;
             }
 
             let idx = self.index;
             self.index += 1;
 
-            let bit_index = idx - offset;
+            if cell.get(idx - offset) {
+                return Some(idx);
+            }
+        }
+    }
+}
 
-            if self.index >= offset + Cell::bits() {
-                self.leaf_layer_cur = self.leaf_layer_iter.next();
+impl<T: CellInt> DoubleEndedIterator for LeafIteratorOnes<T> {
+    fn next_back(&mut self) -> Option<usize> {
+        loop {
+            if self.index >= self.end || self.back == 0 {
+                return None;
+            }
+            let (offset, cell) = self.leaf_layer[self.back - 1];
+            if self.end > offset + Cell::<T>::bits() {
+                self.end = offset + Cell::<T>::bits();
+                continue;
+            }
+            if self.end <= offset {
+                self.back -= 1;
+                continue;
             }
 
-            if cell.get(bit_index) {
+            self.end -= 1;
+            let idx = self.end;
+
+            if cell.get(idx - offset) {
                 return Some(idx);
             }
         }
@@ -272,6 +388,173 @@ mod tests {
         let v: Vec<usize> = tree.iter_ones().collect();
         assert_eq!(vec![17, 19], v);
     }
+
+    #[test]
+    fn tree_iter_range_cell_aligned() {
+        let tree = Tree::from(&[7, 1, 1, 1]).unwrap();
+
+        let v: Vec<usize> = tree.iter_ones_range(32..48).collect();
+        assert_eq!(vec![32], v);
+
+        let v: Vec<bool> = tree.iter_range(32..48).collect();
+        let mut r = vec![false; 16];
+        r[0] = true;
+        assert_eq!(r, v);
+    }
+
+    #[test]
+    fn tree_into_iter() {
+        let tree = Tree::from_ones([0, 17, 300, 4095]);
+
+        let mut count = 0;
+        for (i, b) in (&tree).into_iter().enumerate() {
+            assert_eq!(tree.get(i), b);
+            count += 1;
+        }
+        assert_eq!(tree.bits(), count);
+
+        let mut ones = vec![];
+        for (i, b) in tree.iter().enumerate() {
+            if b {
+                ones.push(i);
+            }
+        }
+        assert_eq!(tree.iter_ones().collect::<Vec<_>>(), ones);
+        assert_eq!(4096, (&tree).into_iter().len());
+    }
+
+    #[test]
+    fn tree_iter_len() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        tree.set(17);
+
+        assert_eq!(4096, tree.iter().len());
+        let mut iter = tree.iter_range(16..32);
+        assert_eq!(16, iter.len());
+        assert_eq!(Some(false), iter.next());
+        assert_eq!(Some(true), iter.next());
+        assert_eq!(14, iter.len());
+        assert_eq!(14, iter.count());
+        assert_eq!(0, tree.iter_range(32..32).len());
+    }
+
+    #[test]
+    fn tree_iter_ones_range_mid_cell() {
+        // bits 16..32 share a single leaf Cell, so `from` lands inside the
+        // first Cell of the range.
+        let tree = Tree::from_ones([16, 17, 18, 19, 31, 32]);
+
+        assert_eq!(
+            vec![18, 19, 31, 32],
+            tree.iter_ones_range(18..tree.bits()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![32, 31, 19, 18],
+            tree.iter_ones_range(18..tree.bits())
+                .rev()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![19], tree.iter_ones_range(19..31).collect::<Vec<_>>());
+        assert_eq!(
+            vec![19],
+            tree.iter_ones_range(19..31).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(0, tree.iter_ones_range(20..31).count());
+    }
+
+    #[test]
+    fn tree_iter_ones_from() {
+        let tree = Tree::from_ones([0, 1, 15, 16, 17, 31, 200, 4095]);
+
+        for start in [0, 1, 2, 16, 17, 18, 199, 200, 201, 4095, 4096, 10000] {
+            assert_eq!(
+                tree.iter_ones()
+                    .filter(|bit| *bit >= start)
+                    .collect::<Vec<_>>(),
+                tree.iter_ones_from(start).collect::<Vec<_>>(),
+                "{}",
+                start
+            );
+        }
+    }
+
+    #[test]
+    fn tree_iter_zeros() {
+        let mut tree = Tree::from_ones([0, 1, 15, 16, 17, 31, 200, 4095]);
+        tree.unset(16);
+
+        let mut all: Vec<usize> = tree.iter_zeros().chain(tree.iter_ones()).collect();
+        assert_eq!(tree.bits() - tree.count_ones(), tree.iter_zeros().count());
+        all.sort_unstable();
+        assert_eq!((0..tree.bits()).collect::<Vec<_>>(), all);
+
+        assert_eq!(
+            vec![2, 3, 4],
+            tree.iter_zeros_range(0..5).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![14, 16, 18],
+            tree.iter_zeros_range(14..19).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![4094],
+            tree.iter_zeros_range(4094..10000).collect::<Vec<_>>()
+        );
+        assert_eq!(0, tree.iter_zeros_range(5000..6000).count());
+        assert_eq!(16, Tree::new().iter_zeros().count());
+    }
+
+    #[test]
+    fn tree_iter_ones_rev() {
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in [0, 3, 15, 16, 17, 300, 2000, 4095] {
+            tree.set(idx);
+        }
+        tree.unset(300);
+
+        let mut ones = tree.iter_ones().collect::<Vec<_>>();
+        ones.reverse();
+        assert_eq!(ones, tree.iter_ones().rev().collect::<Vec<_>>());
+        assert_eq!(
+            vec![2000, 17, 16],
+            tree.iter_ones_range(16..2001).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(None, Tree::with_height(3).iter_ones().next_back());
+
+        // meeting in the middle must not yield any bit twice.
+        let mut iter = tree.iter_ones();
+        assert_eq!(Some(0), iter.next());
+        assert_eq!(Some(4095), iter.next_back());
+        assert_eq!(Some(3), iter.next());
+        assert_eq!(Some(2000), iter.next_back());
+        assert_eq!(vec![15, 16, 17], iter.collect::<Vec<_>>());
+
+        let mut iter = tree.iter_ones_range(15..17);
+        assert_eq!(Some(16), iter.next_back());
+        assert_eq!(Some(15), iter.next_back());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn tree_intersect_sorted() {
+        use crate::std::collections::BTreeSet;
+
+        let mut tree = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in (0..tree.bits()).step_by(3) {
+            tree.set(idx);
+        }
+        let sorted: Vec<usize> = (0..tree.bits()).step_by(5).collect();
+
+        let ones: BTreeSet<usize> = tree.iter_ones().collect();
+        let others: BTreeSet<usize> = sorted.iter().cloned().collect();
+        let expected: Vec<usize> = ones.intersection(&others).cloned().collect();
+
+        let v: Vec<usize> = tree.intersect_sorted(sorted.into_iter()).collect();
+        assert_eq!(expected, v);
+
+        assert_eq!(0, tree.intersect_sorted([1, 2, 4].into_iter()).count());
+        assert_eq!(0, Tree::new().intersect_sorted(0..16).count());
+    }
 }
 
 // vim: foldmethod=marker
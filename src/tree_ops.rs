@@ -0,0 +1,647 @@
+// {{{ Copyright (c) Paul R. Tagliamonte <paultag@gmail.com>, 2024
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE. }}}
+
+use super::{
+    Cell, CellInt, CountOnes, Tree,
+    std::{
+        cmp::Ordering,
+        ops::{BitAnd, BitOr, BitXor},
+        vec,
+        vec::Vec,
+    },
+};
+
+impl<T: CellInt> BitOr for &Tree<T> {
+    type Output = Tree<T>;
+
+    /// Return the [Tree::union] of the two trees.
+    fn bitor(self, other: &Tree<T>) -> Tree<T> {
+        self.union(other)
+    }
+}
+
+impl<T: CellInt> BitAnd for &Tree<T> {
+    type Output = Tree<T>;
+
+    /// Return the [Tree::intersection] of the two trees.
+    fn bitand(self, other: &Tree<T>) -> Tree<T> {
+        self.intersection(other)
+    }
+}
+
+impl<T: CellInt> BitXor for &Tree<T> {
+    type Output = Tree<T>;
+
+    /// Return the [Tree::symmetric_difference] of the two trees.
+    fn bitxor(self, other: &Tree<T>) -> Tree<T> {
+        self.symmetric_difference(other)
+    }
+}
+
+impl<T: CellInt> Tree<T> {
+    /// Return a new [Tree] with every bit set in either `self` or `other`.
+    /// The resulting [Tree] is as tall as the taller of the two trees, and
+    /// will not contain any empty Cells.
+    pub fn union(&self, other: &Tree<T>) -> Tree<T> {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Return a new [Tree] with only the bits set in both `self` and
+    /// `other`. The resulting [Tree] is as tall as the taller of the two
+    /// trees (treating the shorter one as empty above its capacity), and
+    /// will not contain any empty Cells.
+    pub fn intersection(&self, other: &Tree<T>) -> Tree<T> {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Return a new [Tree] with the bits set in `self` which are not set in
+    /// `other`. The resulting [Tree] is as tall as the taller of the two
+    /// trees, and will not contain any empty Cells.
+    pub fn difference(&self, other: &Tree<T>) -> Tree<T> {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    /// Return a new [Tree] with the bits set in exactly one of `self` or
+    /// `other`. The resulting [Tree] is as tall as the taller of the two
+    /// trees, and will not contain any empty Cells -- so the symmetric
+    /// difference of two trees with the same bits set is an empty tree.
+    pub fn symmetric_difference(&self, other: &Tree<T>) -> Tree<T> {
+        self.combine(other, |a, b| a ^ b)
+    }
+
+    /// Set every bit which is set in `other`, modifying `self` in place
+    /// rather than building a new [Tree] like [Tree::union]. If `other` is
+    /// taller, `self` is grown to match. Like [Tree::set_many], any empty
+    /// Cells already in `self` are kept.
+    pub fn union_assign(&mut self, other: &Tree<T>) {
+        self.grow_to(other.bits());
+        self.set_many(&other.iter_ones().collect::<Vec<_>>());
+    }
+
+    /// Unset every bit which is not set in `other`, modifying `self` in
+    /// place rather than building a new [Tree] like [Tree::intersection].
+    /// If `other` is taller, `self` is grown to match. Afterwards, `self` is
+    /// [Tree::prune]-d.
+    pub fn intersection_assign(&mut self, other: &Tree<T>) {
+        self.retain_sorted(other, true);
+    }
+
+    /// Unset every bit which is set in `other`, modifying `self` in place
+    /// rather than building a new [Tree] like [Tree::difference]. If `other`
+    /// is taller, `self` is grown to match. Afterwards, `self` is
+    /// [Tree::prune]-d.
+    pub fn difference_assign(&mut self, other: &Tree<T>) {
+        self.retain_sorted(other, false);
+    }
+
+    /// [Tree::retain] the bits of `self` which are (if `keep` is true) or
+    /// are not (if `keep` is false) set in `other`, walking the set bits of
+    /// both trees together.
+    fn retain_sorted(&mut self, other: &Tree<T>, keep: bool) {
+        self.grow_to(other.bits());
+
+        let mut theirs = other.iter_ones().peekable();
+        self.retain(|bit| {
+            while theirs.next_if(|b| *b < bit).is_some() {}
+            (theirs.peek() == Some(&bit)) == keep
+        });
+    }
+
+    /// Return a new [Tree] with every bit below `bits` set if, and only if,
+    /// it is unset in `self`. The new [Tree] is just tall enough to hold
+    /// `bits` bits, and no bit at or beyond `bits` is set. Every leaf Cell
+    /// below `bits` is written, so for a sparse `self` the result will be
+    /// large. If `bits` is larger than the largest capacity representable by
+    /// a `usize` (see [Tree::grow_to]), a panic will be triggered.
+    pub fn complement_within(&self, bits: usize) -> Tree<T> {
+        let mut bounds = Tree::<T>::default();
+        bounds.grow_to(bits);
+
+        let ours = self.leaf_layer();
+        let mut ours = ours.iter().peekable();
+
+        let cells = (0..bits).step_by(Cell::<T>::bits()).map(|offset| {
+            while ours.next_if(|(a, _)| *a < offset).is_some() {}
+            let cell = match ours.peek() {
+                Some((a, a_cell)) if *a == offset => a_cell.inner(),
+                _ => T::ZERO,
+            };
+            let valid = bits - offset;
+            let mask = if valid >= Cell::<T>::bits() {
+                !T::ZERO
+            } else {
+                (T::ONE << valid) - T::ONE
+            };
+            (offset, Cell::from(!cell & mask))
+        });
+        Tree::from_leaf_cells(bounds.height(), cells)
+    }
+
+    /// Return true if every bit set in `self` is also set in `other`. The
+    /// two trees may have different heights. This descends both trees from
+    /// the root, only into the subtrees `self` has present, and will stop as
+    /// soon as a bit set in `self` is found to be missing from `other`.
+    pub fn is_subset(&self, other: &Tree<T>) -> bool {
+        self.all_leaf_cells(other, false, |a, b| a & !b == T::ZERO)
+    }
+
+    /// Return true if no bit is set in both `self` and `other`. This
+    /// descends both trees from the root, only into the subtrees present in
+    /// both trees, and will stop as soon as a common bit is found, without
+    /// building the [Tree::intersection].
+    pub fn is_disjoint(&self, other: &Tree<T>) -> bool {
+        self.all_leaf_cells(other, true, |a, b| a & b == T::ZERO)
+    }
+
+    /// Return true if `self` and `other` have exactly the same bits set. Unlike
+    /// `==`, this ignores the height of the trees, and any empty Cells left
+    /// behind by [Tree::unset].
+    pub fn semantically_eq(&self, other: &Tree<T>) -> bool {
+        self.is_subset(other) && other.is_subset(self)
+    }
+
+    /// Walk the leaf Cells of `self` from the root down, alongside the leaf
+    /// Cell of `other` at the same offset (or an empty Cell, if `other` has
+    /// none), returning true if `pred` holds for every pair. If `both` is
+    /// set, only the subtrees present in both trees are visited (so `pred`
+    /// must hold whenever either Cell is empty). This stops at the first
+    /// pair for which `pred` does not hold.
+    fn all_leaf_cells(&self, other: &Tree<T>, both: bool, pred: impl Fn(T, T) -> bool) -> bool {
+        let height = self.height().max(other.height());
+        let mut ours = Descent::new(self, height);
+        let mut theirs = Descent::new(other, height);
+        all_leaf_cells_under(&mut ours, &mut theirs, 0, 0, Some(0), both, &pred)
+    }
+
+    /// Build a new [Tree] (as tall as the taller of the two trees) by
+    /// combining the leaf Cells of `self` and `other` which share an offset,
+    /// using `op`. A Cell missing from one of the trees is treated as empty.
+    fn combine(&self, other: &Tree<T>, op: impl Fn(T, T) -> T) -> Tree<T> {
+        Tree::from_leaf_cells(
+            self.height().max(other.height()),
+            merge_leaf_cells(&self.leaf_layer(), &other.leaf_layer(), op),
+        )
+    }
+}
+
+/// Walks the Cells of a [Tree] from the root down, as if it had been grown
+/// (see [Tree::grow]) to be `height` layers tall, keeping track of how many
+/// bits are set before the last Cell visited in each layer. The Cells of
+/// each layer must be visited in order.
+struct Descent<'a, T: CellInt> {
+    layers: Vec<&'a [Cell<T>]>,
+
+    /// number of layers "grown" above the root of the [Tree].
+    pad: usize,
+
+    /// index of the last Cell visited in each layer, and the number of bits
+    /// set in the Cells before it.
+    cursors: Vec<(usize, usize)>,
+}
+
+impl<'a, T: CellInt> Descent<'a, T> {
+    fn new(tree: &'a Tree<T>, height: usize) -> Self {
+        Self {
+            layers: tree.layers().collect(),
+            pad: height - tree.height(),
+            cursors: vec![(0, 0); tree.height()],
+        }
+    }
+
+    /// Return the number of layers being walked.
+    fn height(&self) -> usize {
+        self.pad + self.layers.len()
+    }
+
+    /// Return Cell `cell_index` of layer `layer_index`.
+    fn cell(&self, layer_index: usize, cell_index: usize) -> Cell<T> {
+        match layer_index.checked_sub(self.pad) {
+            Some(layer_index) => self.layers[layer_index][cell_index],
+            None => Cell::from(T::ONE),
+        }
+    }
+
+    /// Return the index (in layer `layer_index + 1`) of the Cell under bit
+    /// `idx` of Cell `cell_index` of layer `layer_index`.
+    fn child(&mut self, layer_index: usize, cell_index: usize, idx: usize) -> usize {
+        let Some(layer_index) = layer_index.checked_sub(self.pad) else {
+            return 0;
+        };
+        let layer = self.layers[layer_index];
+        let (last, before) = &mut self.cursors[layer_index];
+        *before += layer[*last..cell_index].count_ones();
+        *last = cell_index;
+        *before + layer[cell_index].count_ones_until(idx)
+    }
+}
+
+/// Walk the leaf Cells under Cell `a` of layer `layer_index` of `ours`,
+/// alongside the Cells under Cell `b` of `theirs` (if it has one), like
+/// [Tree::all_leaf_cells].
+fn all_leaf_cells_under<T: CellInt>(
+    ours: &mut Descent<T>,
+    theirs: &mut Descent<T>,
+    layer_index: usize,
+    a: usize,
+    b: Option<usize>,
+    both: bool,
+    pred: &impl Fn(T, T) -> bool,
+) -> bool {
+    let cell = ours.cell(layer_index, a);
+    let other = b.map_or(Cell::new(), |b| theirs.cell(layer_index, b));
+    if layer_index + 1 == ours.height() {
+        return pred(cell.inner(), other.inner());
+    }
+
+    let children = if both {
+        cell.inner() & other.inner()
+    } else {
+        cell.inner()
+    };
+    for idx in Cell::from(children).iter_ones() {
+        let a = ours.child(layer_index, a, idx);
+        let b = match b {
+            Some(b) if other.get(idx) => Some(theirs.child(layer_index, b, idx)),
+            _ => None,
+        };
+        if !all_leaf_cells_under(ours, theirs, layer_index + 1, a, b, both, pred) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Walk two sorted sets of leaf Cells, combining the Cells at each offset
+/// using `op`. If only one of the sets has a Cell at a given offset, the
+/// other is treated as an empty Cell.
+pub(crate) fn merge_leaf_cells<T: CellInt>(
+    ours: &[(usize, Cell<T>)],
+    theirs: &[(usize, Cell<T>)],
+    op: impl Fn(T, T) -> T,
+) -> Vec<(usize, Cell<T>)> {
+    let mut ret = Vec::with_capacity(ours.len().max(theirs.len()));
+    let (mut ours, mut theirs) = (ours.iter().peekable(), theirs.iter().peekable());
+
+    loop {
+        let (offset, cell) = match (ours.peek(), theirs.peek()) {
+            (None, None) => break,
+            (Some(&&(a, a_cell)), Some(&&(b, b_cell))) => match a.cmp(&b) {
+                Ordering::Less => {
+                    ours.next();
+                    (a, op(a_cell.inner(), T::ZERO))
+                }
+                Ordering::Greater => {
+                    theirs.next();
+                    (b, op(T::ZERO, b_cell.inner()))
+                }
+                Ordering::Equal => {
+                    ours.next();
+                    theirs.next();
+                    (a, op(a_cell.inner(), b_cell.inner()))
+                }
+            },
+            (Some(&&(a, a_cell)), None) => {
+                ours.next();
+                (a, op(a_cell.inner(), T::ZERO))
+            }
+            (None, Some(&&(b, b_cell))) => {
+                theirs.next();
+                (b, op(T::ZERO, b_cell.inner()))
+            }
+        };
+        ret.push((offset, cell.into()));
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::vec;
+
+    #[test]
+    fn tree_union() {
+        let a = Tree::from(&[1, 10]).unwrap();
+        let b = Tree::from(&[2, 10]).unwrap();
+
+        let union = a.union(&b);
+        assert_eq!(vec![1, 3, 17, 19], union.iter_ones().collect::<Vec<_>>());
+        assert_eq!(Tree::from(&[3, 10, 10]).unwrap(), union);
+        assert_eq!(union, b.union(&a));
+        assert_eq!(a, a.union(&a));
+    }
+
+    #[test]
+    fn tree_union_heights() {
+        let a = Tree::from(&[1, 10]).unwrap();
+        let mut b = Tree::from(&[1, 1, 0]).unwrap();
+        b.set(4000);
+
+        let union = a.union(&b);
+        assert_eq!(3, union.height());
+        assert_eq!(vec![1, 3, 4000], union.iter_ones().collect::<Vec<_>>());
+        assert_eq!(Tree::from_sorted_ones(3, [1, 3, 4000]), union);
+        assert_eq!(union, b.union(&a));
+    }
+
+    #[test]
+    fn tree_intersection() {
+        let mut full = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in 0..full.bits() {
+            full.set(idx);
+        }
+        let mut one = Tree::from(&[1, 1, 0]).unwrap();
+        one.set(2049);
+
+        let intersection = full.intersection(&one);
+        assert_eq!(vec![2049], intersection.iter_ones().collect::<Vec<_>>());
+        assert_eq!(Tree::from_sorted_ones(3, [2049]), intersection);
+        assert_eq!(intersection, one.intersection(&full));
+    }
+
+    #[test]
+    fn tree_intersection_heights() {
+        let a = Tree::from(&[3, 10, 10]).unwrap();
+        let mut b = Tree::from(&[1, 1, 0]).unwrap();
+        b.set(3);
+        b.set(19);
+        b.set(4000);
+
+        let intersection = a.intersection(&b);
+        assert_eq!(3, intersection.height());
+        assert_eq!(Tree::from_sorted_ones(3, [3, 19]), intersection);
+        assert_eq!(intersection, b.intersection(&a));
+    }
+
+    #[test]
+    fn tree_intersection_empty() {
+        let a = Tree::from(&[1, 10]).unwrap();
+        let b = Tree::from(&[2, 10]).unwrap();
+
+        let intersection = a.intersection(&b);
+        assert_eq!(vec![0], intersection.to_vec());
+        assert_eq!(2, intersection.height());
+    }
+
+    #[test]
+    fn tree_ops_operators() {
+        let a = Tree::from_ones([1, 3, 17, 4000]);
+        let b = Tree::from_ones([3, 19, 200]);
+
+        assert_eq!(a.union(&b), &a | &b);
+        assert_eq!(a.intersection(&b), &a & &b);
+        assert_eq!(a.symmetric_difference(&b), &a ^ &b);
+        assert_eq!(vec![3], (&a & &b).iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tree_complement_within() {
+        let a = Tree::from_ones([1, 3, 17, 40]);
+
+        let complement = a.complement_within(20);
+        assert_eq!(2, complement.height());
+        assert_eq!(
+            vec![0, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 19],
+            complement.iter_ones().collect::<Vec<_>>()
+        );
+        assert!(complement.is_disjoint(&a));
+
+        assert_eq!(
+            vec![1, 3, 17],
+            complement
+                .complement_within(20)
+                .iter_ones()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Tree::from_sorted_ones(2, [1, 3, 17]),
+            complement.complement_within(20)
+        );
+
+        assert_eq!(16, Tree::new().complement_within(16).count_ones());
+        assert_eq!(0, a.complement_within(0).count_ones());
+    }
+
+    #[test]
+    fn tree_complement_within_u64() {
+        let a: Tree<u64> = [0, 63, 64, 100].into_iter().collect();
+        let complement = a.complement_within(128);
+        assert_eq!(124, complement.count_ones());
+        assert_eq!(a, complement.complement_within(128));
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity out of range")]
+    fn tree_complement_within_overflow() {
+        Tree::new().complement_within(usize::MAX);
+    }
+
+    #[test]
+    fn tree_is_subset() {
+        let mut full = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in 0..full.bits() {
+            full.set(idx);
+        }
+        let one = Tree::from_ones([2049]);
+
+        assert!(one.is_subset(&full));
+        assert!(!full.is_subset(&one));
+        assert!(full.is_subset(&full));
+
+        let a = Tree::from_ones([3, 17]);
+        let b = Tree::from_ones([4, 18]);
+        assert!(!a.is_subset(&b));
+        assert!(!b.is_subset(&a));
+
+        // empty trees are a subset of everything, no matter the height.
+        assert!(Tree::new().is_subset(&a));
+        assert!(Tree::with_height(4).is_subset(&a));
+        assert!(!a.is_subset(&Tree::with_height(4)));
+
+        // unset bits leave empty Cells behind, which must be ignored.
+        let mut c = Tree::from_ones([3, 17, 4000]);
+        c.unset(4000);
+        assert!(c.is_subset(&a));
+    }
+
+    #[test]
+    fn tree_is_disjoint() {
+        let a = Tree::from_ones([3, 17, 4000]);
+        let b = Tree::from_ones([4000, 5]);
+        assert!(!a.is_disjoint(&b));
+        assert!(!b.is_disjoint(&a));
+
+        // neighboring bits within the same Cell don't overlap.
+        let c = Tree::from_ones([2, 4, 16, 18]);
+        assert!(a.is_disjoint(&c));
+        assert!(c.is_disjoint(&a));
+
+        assert!(!a.is_disjoint(&a));
+        assert!(Tree::new().is_disjoint(&Tree::new()));
+        assert!(Tree::with_height(3).is_disjoint(&a));
+        assert!(a.is_disjoint(&Tree::new()));
+    }
+
+    #[test]
+    fn tree_is_subset_disjoint_walk() {
+        use crate::std::collections::BTreeSet;
+
+        let mut sparse = Tree::from_ones([3, 17, 300, 301, 4000, 70000]);
+        sparse.unset(301);
+        let trees = [
+            Tree::new(),
+            Tree::from_ones([3]),
+            Tree::from_ones([3, 17, 4000]),
+            Tree::from_ones([4, 18, 4001]),
+            Tree::from_ones([16, 17, 300, 1025, 2049, 70000]),
+            Tree::from_ones((0..5000).step_by(7)),
+            Tree::from_ones((0..5000).step_by(3)),
+            Tree::from_ones([70000]),
+            sparse,
+        ];
+
+        for x in trees.iter() {
+            for y in trees.iter() {
+                let ones = |tree: &Tree| tree.iter_ones().collect::<BTreeSet<_>>();
+                let (xs, ys) = (ones(x), ones(y));
+                assert_eq!(xs.is_subset(&ys), x.is_subset(y));
+                assert_eq!(xs.is_disjoint(&ys), x.is_disjoint(y));
+            }
+        }
+    }
+
+    #[test]
+    fn tree_ops_assign() {
+        let a = Tree::from_ones([1, 3, 17, 40]);
+        let b = Tree::from_ones([3, 19, 40, 4000]);
+
+        for (x, y) in [(&a, &b), (&b, &a)] {
+            let mut union = x.clone();
+            union.union_assign(y);
+            assert_eq!(x | y, union);
+
+            let mut intersection = x.clone();
+            intersection.intersection_assign(y);
+            assert_eq!(x & y, intersection);
+
+            let mut difference = x.clone();
+            difference.difference_assign(y);
+            assert_eq!(x.difference(y), difference);
+        }
+
+        let mut empty = a.clone();
+        empty.intersection_assign(&Tree::new());
+        assert_eq!(Tree::with_height(a.height()), empty);
+
+        let mut empty = a.clone();
+        empty.difference_assign(&a);
+        assert_eq!(Tree::with_height(a.height()), empty);
+    }
+
+    #[test]
+    fn tree_semantically_eq() {
+        let fresh = Tree::from_ones([1, 17]);
+
+        let mut toggled = Tree::from_ones([1, 17, 4000]);
+        toggled.unset(4000);
+        assert_ne!(fresh, toggled);
+        assert!(fresh.semantically_eq(&toggled));
+        assert!(toggled.semantically_eq(&fresh));
+
+        toggled.unset(17);
+        assert!(!fresh.semantically_eq(&toggled));
+        assert!(!toggled.semantically_eq(&fresh));
+
+        assert!(Tree::new().semantically_eq(&Tree::with_height(3)));
+        assert!(!Tree::new().semantically_eq(&fresh));
+    }
+
+    #[test]
+    fn tree_difference() {
+        let a = Tree::from(&[3, 10, 10]).unwrap();
+        let b = Tree::from(&[1, 0x22]).unwrap();
+
+        let difference = a.difference(&b);
+        assert_eq!(vec![3, 17, 19], difference.iter_ones().collect::<Vec<_>>());
+        assert_eq!(vec![5], b.difference(&a).iter_ones().collect::<Vec<_>>());
+        assert_eq!(vec![0], a.difference(&a).to_vec());
+    }
+
+    #[test]
+    fn tree_difference_collapse() {
+        let mut a = Tree::from(&[1, 1, 0]).unwrap();
+        let mut b = a.clone();
+        for idx in [5, 300, 310, 4000] {
+            a.set(idx);
+        }
+        for idx in [300, 310] {
+            b.set(idx);
+        }
+
+        // removing 300 and 310 empties the subtree under root bit 1, so
+        // the 2nd layer loses a Cell as well.
+        let difference = a.difference(&b);
+        assert_eq!(vec![5, 4000], difference.iter_ones().collect::<Vec<_>>());
+
+        let mut fresh = Tree::from(&[1, 1, 0]).unwrap();
+        fresh.set(5);
+        fresh.set(4000);
+        assert_eq!(fresh.to_vec(), difference.to_vec());
+    }
+
+    #[test]
+    fn tree_symmetric_difference() {
+        let a = Tree::from(&[3, 10, 10]).unwrap();
+        let b = Tree::from(&[1, 0x22]).unwrap();
+
+        let xor = a.symmetric_difference(&b);
+        assert_eq!(vec![3, 5, 17, 19], xor.iter_ones().collect::<Vec<_>>());
+        assert_eq!(Tree::from(&[3, 0x28, 10]).unwrap(), xor);
+        assert_eq!(xor, b.symmetric_difference(&a));
+    }
+
+    #[test]
+    fn tree_symmetric_difference_self() {
+        let mut a = Tree::from(&[1, 1, 0]).unwrap();
+        for idx in [5, 300, 310, 4000] {
+            a.set(idx);
+        }
+
+        let xor = a.symmetric_difference(&a);
+        assert_eq!(None, xor.iter_ones().next());
+        assert_eq!(Tree::with_height(3), xor);
+        assert_eq!(Tree::new(), Tree::new().symmetric_difference(&Tree::new()));
+    }
+
+    #[test]
+    fn tree_union_canonical() {
+        let mut a = Tree::from(&[1, 1, 0]).unwrap();
+        a.set(100);
+        a.set(200);
+        a.unset(100);
+
+        let union = a.union(&Tree::new());
+        assert_eq!(vec![200], union.iter_ones().collect::<Vec<_>>());
+        assert_eq!(a.compact_to_vec(), union.to_vec());
+    }
+}
+
+// vim: foldmethod=marker
@@ -0,0 +1,107 @@
+// {{{ Copyright (c) Paul R. Tagliamonte <paultag@gmail.com>, 2024
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE. }}}
+
+use super::{Cell, CellInt, Tree, std::vec::Vec, tree_ops::merge_leaf_cells};
+use rayon::prelude::*;
+
+/// Number of leaf Cells from `self` handled by each parallel job.
+const CHUNK_CELLS: usize = 1024;
+
+impl<T: CellInt> Tree<T> {
+    /// Return a [Tree] with only the bits set in both `self` and `other`,
+    /// computed in parallel using [rayon]. Leaf Cells at different offsets
+    /// are independent, so the leaf layer of `self` is split into chunks,
+    /// each of which is intersected against the matching Cells of `other`,
+    /// and the results are stitched back together into a single [Tree].
+    ///
+    /// The result is the same as [Tree::intersection].
+    pub fn par_and(&self, other: &Tree<T>) -> Tree<T> {
+        let height = self.height().max(other.height());
+        let ours = self.leaf_layer();
+        let theirs = other.leaf_layer();
+
+        let chunks: Vec<Vec<(usize, Cell<T>)>> = ours
+            .par_chunks(CHUNK_CELLS)
+            .map(|chunk| {
+                let (first, last) = (chunk[0].0, chunk[chunk.len() - 1].0);
+                let start = theirs.partition_point(|(offset, _)| *offset < first);
+                let end = theirs.partition_point(|(offset, _)| *offset <= last);
+                merge_leaf_cells(chunk, &theirs[start..end], |a, b| a & b)
+            })
+            .collect();
+
+        Tree::from_leaf_cells(height, chunks.into_iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::vec;
+
+    fn sparse(height: usize, step: usize) -> Tree {
+        let mut tree = Tree::new();
+        while tree.height() < height {
+            tree.grow();
+        }
+        for idx in (0..tree.bits()).step_by(step) {
+            tree.set(idx);
+        }
+        tree
+    }
+
+    #[test]
+    fn tree_par_and() {
+        let a = sparse(4, 7);
+        let b = sparse(4, 11);
+
+        let serial = a.intersection(&b);
+        let parallel = a.par_and(&b);
+        assert_eq!(serial, parallel);
+        assert_eq!(serial, b.par_and(&a));
+        assert_eq!(
+            (0..a.bits()).step_by(77).collect::<Vec<_>>(),
+            parallel.iter_ones().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn tree_par_and_heights() {
+        let a = sparse(4, 3);
+        let b = sparse(2, 5);
+
+        let parallel = a.par_and(&b);
+        assert_eq!(4, parallel.height());
+        assert_eq!(a.intersection(&b), parallel);
+        assert_eq!(Tree::from_sorted_ones(4, (0..256).step_by(15)), parallel);
+        assert_eq!(parallel, b.par_and(&a));
+    }
+
+    #[test]
+    fn tree_par_and_empty() {
+        let a = sparse(3, 2);
+        let b = Tree::from(&[1, 1, 0]).unwrap();
+
+        assert_eq!(vec![0], a.par_and(&b).to_vec());
+        assert_eq!(vec![0], b.par_and(&a).to_vec());
+    }
+}
+
+// vim: foldmethod=marker
@@ -0,0 +1,87 @@
+// {{{ Copyright (c) Paul R. Tagliamonte <paultag@gmail.com>, 2024
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE. }}}
+
+use super::{CellInt, Tree, std::vec::Vec};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+impl<T: CellInt + Serialize> Serialize for Tree<T> {
+    /// Serialize the [Tree] as the sequence of Cells from [Tree::to_vec].
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.to_vec())
+    }
+}
+
+impl<'de, T: CellInt + Deserialize<'de>> Deserialize<'de> for Tree<T> {
+    /// Deserialize a sequence of Cells, reconstructing the [Tree] with
+    /// [Tree::from_cells]. Malformed Cells are returned as an error.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cells = Vec::<T>::deserialize(deserializer)?;
+        Tree::from_cells(&cells)
+            .map_err(|err| de::Error::custom(format_args!("invalid k2 tree: {:?}", err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::std::{string::ToString, vec};
+
+    #[test]
+    fn tree_serde_json() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!("[32769,2,32768,10,32768]", json);
+        assert_eq!(tree, serde_json::from_str::<Tree>(&json).unwrap());
+    }
+
+    #[test]
+    fn tree_serde_json_u64() {
+        let tree: Tree<u64> = [17, 19, 4095].into_iter().collect();
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(tree, serde_json::from_str::<Tree<u64>>(&json).unwrap());
+        assert!(serde_json::from_str::<Tree<u8>>(&json).is_err());
+    }
+
+    #[test]
+    fn tree_serde_bincode() {
+        let tree = Tree::from_ones([17, 19, 4095]);
+
+        let bytes = bincode::serialize(&tree).unwrap();
+        assert_eq!(tree, bincode::deserialize::<Tree>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn tree_serde_malformed() {
+        assert!(serde_json::from_str::<Tree>("[]").is_err());
+        assert!(serde_json::from_str::<Tree>("[0, 0]").is_err());
+        assert!(serde_json::from_str::<Tree>("[3, 1]").is_err());
+        assert!(serde_json::from_str::<Tree>("[70000]").is_err());
+
+        let err = serde_json::from_str::<Tree>("[3, 1]").unwrap_err();
+        assert!(err.to_string().starts_with("invalid k2 tree: Malformed"));
+
+        let bytes = bincode::serialize(&vec![1u16, 3, 1]).unwrap();
+        assert!(bincode::deserialize::<Tree>(&bytes).is_err());
+    }
+}
+
+// vim: foldmethod=marker